@@ -1,7 +1,10 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::Args;
 
+use crate::entities::export::{parse_report_target, ReportTarget};
+use crate::entities::limits::{parse_duration, parse_size, ResourceLimits};
 use crate::PackageManagers;
 
 #[derive(Args, Debug, Clone)]
@@ -9,7 +12,8 @@ pub struct New {
     /// Name of project
     pub name: Option<String>,
 
-    /// Select package manager you would like to use
+    /// Select package manager you would like to use, `compiler` in .ciri.toml picks between
+    /// gcc and clang for C and C++
     #[arg(short, long)]
     pub manager: PackageManagers,
 
@@ -30,6 +34,36 @@ pub struct Build {
     /// Watch changes and restart
     #[arg(short, long)]
     pub watch: bool,
+
+    /// Build with optimizations (CMAKE_BUILD_TYPE=Release for C/C++)
+    #[arg(long, conflicts_with = "debug")]
+    pub release: bool,
+
+    /// Build with debug information (CMAKE_BUILD_TYPE=Debug for C/C++)
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Built-in profile (release, debug) or one defined in [profiles] of .ciri.toml
+    #[arg(long, conflicts_with_all = ["release", "debug"])]
+    pub profile: Option<String>,
+
+    /// Build even if inputs didn't change since last build
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print summary as a JSON record on stdout
+    #[arg(long)]
+    pub json: bool,
+
+    /// Write compiler messages for CI, junit=<path> or json=<path>, can be repeated
+    ///
+    /// Schema: https://github.com/ponurakk/ciri/blob/main/docs/reports.md
+    #[arg(long, value_parser = parse_report_target)]
+    pub report: Vec<ReportTarget>,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
 }
 
 impl Build {
@@ -38,6 +72,13 @@ impl Build {
             name,
             script,
             watch,
+            release: false,
+            debug: false,
+            profile: None,
+            force: false,
+            json: false,
+            report: vec![],
+            args: vec![],
         }
     }
 }
@@ -54,11 +95,75 @@ pub struct Run {
     /// Watch changes and restart
     #[arg(short, long)]
     pub watch: bool,
+
+    /// Build with optimizations (CMAKE_BUILD_TYPE=Release for C/C++)
+    #[arg(long, conflicts_with = "debug")]
+    pub release: bool,
+
+    /// Build with debug information (CMAKE_BUILD_TYPE=Debug for C/C++)
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Built-in profile (release, debug) or one defined in [profiles] of .ciri.toml
+    #[arg(long, conflicts_with_all = ["release", "debug"])]
+    pub profile: Option<String>,
+
+    /// Kill process group after this wall time, e.g. 30s or 5m
+    #[arg(long, value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Limit memory of the process, e.g. 512M or 2G
+    #[arg(long, value_parser = parse_size)]
+    pub memory_limit: Option<u64>,
+
+    /// Limit CPU time of the process, e.g. 1m
+    #[arg(long, value_parser = parse_duration)]
+    pub cpu_time: Option<Duration>,
+
+    /// Load .env.<name> and [env.<name>] of .ciri.toml
+    #[arg(long)]
+    pub env_profile: Option<String>,
+
+    /// Print variables loaded from .env files and config, secrets are masked
+    #[arg(long)]
+    pub print_env: bool,
+
+    /// Build even if inputs didn't change since last build
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print summary as a JSON record on stdout
+    #[arg(long)]
+    pub json: bool,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
 }
 
 impl Run {
     pub fn new(name: Option<PathBuf>, build: bool, watch: bool) -> Self {
-        Self { name, build, watch }
+        Self {
+            name,
+            build,
+            watch,
+            release: false,
+            debug: false,
+            profile: None,
+            timeout: None,
+            memory_limit: None,
+            cpu_time: None,
+            env_profile: None,
+            print_env: false,
+            force: false,
+            json: false,
+            args: vec![],
+        }
+    }
+
+    /// Limits from flags, with defaults from config
+    pub fn limits(&self) -> miette::Result<ResourceLimits> {
+        ResourceLimits::load(self.timeout, self.memory_limit, self.cpu_time)
     }
 }
 
@@ -70,25 +175,166 @@ pub struct Test {
     /// Watch changes and restart
     #[arg(short, long)]
     pub watch: bool,
+
+    /// Build with optimizations (CMAKE_BUILD_TYPE=Release for C/C++)
+    #[arg(long, conflicts_with = "debug")]
+    pub release: bool,
+
+    /// Build with debug information (CMAKE_BUILD_TYPE=Debug for C/C++)
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Built-in profile (release, debug) or one defined in [profiles] of .ciri.toml
+    #[arg(long, conflicts_with_all = ["release", "debug"])]
+    pub profile: Option<String>,
+
+    /// Kill process group after this wall time, e.g. 30s or 5m
+    #[arg(long, value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Limit memory of the process, e.g. 512M or 2G
+    #[arg(long, value_parser = parse_size)]
+    pub memory_limit: Option<u64>,
+
+    /// Limit CPU time of the process, e.g. 1m
+    #[arg(long, value_parser = parse_duration)]
+    pub cpu_time: Option<Duration>,
+
+    /// Load .env.<name> and [env.<name>] of .ciri.toml
+    #[arg(long)]
+    pub env_profile: Option<String>,
+
+    /// Print variables loaded from .env files and config, secrets are masked
+    #[arg(long)]
+    pub print_env: bool,
+
+    /// Print summary as a JSON record on stdout
+    #[arg(long)]
+    pub json: bool,
+
+    /// Browse failed tests and their output in a table after tests finish
+    #[arg(long)]
+    pub browse: bool,
+
+    /// Run only tests that failed last time
+    #[arg(long, conflicts_with = "name")]
+    pub failed: bool,
+
+    /// Run failed tests again up to this many times, ones that pass are reported as flaky
+    #[arg(long, default_value_t = 0)]
+    pub retries: u32,
+
+    /// Write test results for CI, junit=<path> or json=<path>, can be repeated
+    ///
+    /// Schema: https://github.com/ponurakk/ciri/blob/main/docs/reports.md
+    #[arg(long, value_parser = parse_report_target)]
+    pub report: Vec<ReportTarget>,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+impl Test {
+    pub fn new(name: Option<PathBuf>, watch: bool) -> Self {
+        Self {
+            name,
+            watch,
+            release: false,
+            debug: false,
+            profile: None,
+            timeout: None,
+            memory_limit: None,
+            cpu_time: None,
+            env_profile: None,
+            print_env: false,
+            json: false,
+            browse: false,
+            failed: false,
+            retries: 0,
+            report: vec![],
+            args: vec![],
+        }
+    }
+
+    /// Limits from flags, with defaults from config
+    pub fn limits(&self) -> miette::Result<ResourceLimits> {
+        ResourceLimits::load(self.timeout, self.memory_limit, self.cpu_time)
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct Start {
+    /// Processes to start, all of them by default
+    pub names: Vec<String>,
+
+    /// Start processes again when they exit, all of them if no names are given
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    pub restart_on_exit: Option<Vec<String>>,
+
+    /// Load .env.<name> and [env.<name>] of .ciri.toml
+    #[arg(long)]
+    pub env_profile: Option<String>,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct History {
+    /// Show every project, not only the current one
+    #[arg(short, long)]
+    pub all: bool,
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct Add {
     /// Name of package to add
     pub name: String,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+impl Add {
+    pub fn new(name: String) -> Self {
+        Self { name, args: vec![] }
+    }
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct Remove {
     /// Name of package to remove
     pub name: String,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+impl Remove {
+    pub fn new(name: String) -> Self {
+        Self { name, args: vec![] }
+    }
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct Update {
     /// Name of package to update
     pub name: Option<String>,
+
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
 }
 
-#[derive(Args, Debug, Clone)]
-pub struct Clean {}
+impl Update {
+    pub fn new(name: Option<String>) -> Self {
+        Self { name, args: vec![] }
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct Clean {
+    /// Arguments passed to the underlying tool
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
@@ -0,0 +1,206 @@
+use std::cmp::Ordering;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ciri::args::package::Add;
+use ciri::entities::managers::Manager;
+use ciri::entities::manifest::{ConanFile, ScalaDependency, VcpkgManifest};
+use ciri::validators::detect_language;
+use ciri::{Config, PackageManagers, Util};
+use duct::cmd;
+use inquire::Select;
+use miette::{bail, IntoDiagnostic};
+
+pub fn add(args: Add) -> miette::Result<()> {
+    let langs = detect_language()?;
+    match langs.len().cmp(&1) {
+        Ordering::Less => handle_none()?,
+        Ordering::Equal => add_one(langs.first().unwrap(), args)?,
+        Ordering::Greater => add_multiple(langs, args)?,
+    }
+
+    Ok(())
+}
+
+fn add_one(lang: &str, args: Add) -> miette::Result<()> {
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
+    let pkg = bind.to_manager()?;
+
+    match bind {
+        PackageManagers::Bun
+        | PackageManagers::Npm
+        | PackageManagers::Yarn
+        | PackageManagers::Pnpm
+        | PackageManagers::Cargo
+        | PackageManagers::Lua
+        | PackageManagers::Swift => add_from_manager(args, pkg),
+        PackageManagers::Scala => add_scala(args, pkg),
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => add_native(args),
+        PackageManagers::Make | PackageManagers::Just => {
+            bail!("Dependencies can't be added to {} projects", pkg.agent)
+        }
+        _ => bail!("Package manager was not yet implemented"),
+    }
+}
+
+fn add_from_manager(args: Add, pkg: Manager) -> miette::Result<()> {
+    let (program, mut p) = pkg.add.to_args()?;
+    p.push(&args.name);
+    pkg.push_args("add", &mut p, &args.args);
+    cmd(program, p).run().into_diagnostic()?;
+    Ok(())
+}
+
+/// Adds dependency to build.sbt, or as directive to project.scala of scala-cli
+fn add_scala(args: Add, pkg: Manager) -> miette::Result<()> {
+    if !args.args.is_empty() {
+        warn!("Extra arguments are ignored when editing manifest");
+    }
+
+    let dependency = ScalaDependency::from_str(&args.name)?;
+    let path = PathBuf::from(if pkg.agent == "sbt" {
+        "build.sbt"
+    } else {
+        "project.scala"
+    });
+    let content = if path.exists() {
+        std::fs::read_to_string(&path).into_diagnostic()?
+    } else {
+        String::new()
+    };
+
+    let content = if pkg.agent == "sbt" {
+        dependency.add_to_sbt(&content)
+    } else {
+        dependency.add_to_scala_cli(&content)
+    };
+    std::fs::write(&path, content).into_diagnostic()?;
+    info!("Added {} to {}", dependency, path.display());
+    Ok(())
+}
+
+/// Adds dependency to conanfile.txt when Conan is used, otherwise to vcpkg manifest
+fn add_native(args: Add) -> miette::Result<()> {
+    if !args.args.is_empty() {
+        warn!("Extra arguments are ignored when editing manifest");
+    }
+
+    let conanfile = PathBuf::from("conanfile.txt");
+    if conanfile.exists() {
+        if !args.name.contains('/') {
+            bail!(
+                "Conan needs a version of \"{}\", use \"{}/<version>\" instead",
+                args.name,
+                args.name
+            );
+        }
+
+        let mut conan = ConanFile::try_from(conanfile.clone())?;
+        conan.add_require(&args.name);
+        std::fs::write(&conanfile, conan.to_string()).into_diagnostic()?;
+        info!("Added {} to conanfile.txt", args.name);
+        return Ok(());
+    }
+
+    let path = PathBuf::from("vcpkg.json");
+    let mut manifest = if path.exists() {
+        VcpkgManifest::try_from(path.clone())?
+    } else {
+        let current_dir = env::current_dir().into_diagnostic()?;
+        let name = current_dir
+            .file_name()
+            .and_then(|v| v.to_str())
+            .unwrap_or("app");
+        VcpkgManifest::new(name)
+    };
+
+    if manifest.add_dependency(&args.name) {
+        manifest.save(Path::new("vcpkg.json"))?;
+        info!("Added {} to vcpkg.json", args.name);
+    } else {
+        warn!("{} is already a dependency", args.name);
+    }
+
+    Ok(())
+}
+
+fn add_multiple(langs: Vec<String>, args: Add) -> miette::Result<()> {
+    let manager = Select::new("What package manager would you use?", langs)
+        .prompt()
+        .into_diagnostic()?;
+    add_one(manager.as_str(), args)
+}
+
+fn handle_none() -> miette::Result<()> {
+    bail!("No valid package manager was detected")
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    fn prepare_add_test(name: &str) -> anyhow::Result<()> {
+        std::fs::create_dir_all(format!("/tmp/ciri/add_test/{}", name))?;
+        cmd!(
+            "cp",
+            "-r",
+            format!("{}/example_projects/{}/.", env!("CARGO_MANIFEST_DIR"), name),
+            format!("/tmp/ciri/add_test/{}", name)
+        )
+        .run()?;
+
+        env::set_current_dir(format!("/tmp/ciri/add_test/{}", name))?;
+
+        Ok(())
+    }
+
+    fn clean(name: &str) -> anyhow::Result<()> {
+        std::fs::remove_dir_all(format!("/tmp/ciri/add_test/{}", name))?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn add_cpp_test() -> anyhow::Result<()> {
+        prepare_add_test("cpp")?;
+
+        let res = add(Add::new("fmt".to_owned()));
+        assert!(res.is_ok());
+        let manifest = VcpkgManifest::try_from(PathBuf::from("vcpkg.json")).unwrap();
+        assert_eq!(manifest.dependency_names(), vec!["fmt"]);
+
+        std::fs::write("conanfile.txt", "[requires]\n")?;
+        let res = add(Add::new("fmt".to_owned()));
+        assert!(res.is_err());
+
+        let res = add(Add::new("fmt/10.2.1".to_owned()));
+        assert!(res.is_ok());
+        assert_eq!(
+            std::fs::read_to_string("conanfile.txt")?,
+            "[requires]\nfmt/10.2.1\n"
+        );
+
+        clean("cpp")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn add_make_test() -> anyhow::Result<()> {
+        prepare_add_test("make")?;
+
+        let res = add(Add::new("foo".to_owned()));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Dependencies can't be added to make projects"
+        );
+
+        clean("make")?;
+        Ok(())
+    }
+}
@@ -1,27 +1,82 @@
 use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use ciri::args::package::Build;
+use ciri::entities::build_system::is_source_file;
+use ciri::entities::export::build_report;
+use ciri::entities::limits::ResourceLimits;
 use ciri::entities::managers::Manager;
+use ciri::entities::manifest::Rockspec;
+use ciri::entities::profile::ResolvedProfile;
+use ciri::entities::task_runner::TaskRunner;
+use ciri::parsers::compiler::OutputFormat;
 use ciri::validators::detect_language;
-use ciri::{PackageManagers, Util};
+use ciri::{Config, PackageManagers, Util};
 use duct::cmd;
 use inquire::Select;
 use miette::{bail, IntoDiagnostic};
 
+use super::fingerprint::{project_inputs, unless_fresh};
+use super::native::{build_file, build_project};
+use super::process::{run_child, run_parsed, take_messages};
+use super::task_runner::build_from_tasks;
+use super::tasks::{run_override, task_commands, with_build_hooks};
+
 pub fn build(args: Build) -> miette::Result<()> {
-    let langs = detect_language()?;
-    match langs.len().cmp(&1) {
-        Ordering::Less => handle_none()?,
-        Ordering::Equal => build_one(langs.get(0).unwrap(), args)?,
-        Ordering::Greater => build_multiple(langs, args)?,
-    }
+    let targets = args.report.clone();
+    let result = with_build_hooks(args.force, || {
+        // Build task is usually a script which always does everything again
+        let config = Config::load()?;
+        if let Some(tasks) = config
+            .tasks
+            .as_ref()
+            .filter(|v| args.name.is_none() && v.contains_key("build"))
+        {
+            let inputs = project_inputs(&config)?;
+            let mut extra = args.args.clone();
+            extra.extend(task_commands(tasks, "build")?);
+            let ran = unless_fresh("build", &inputs, &extra, args.force, || {
+                run_override("build", &args.args, &ResourceLimits::default()).map(drop)
+            })?;
+            if !ran {
+                info!("Build inputs didn't change, skipping build task");
+            }
+            return Ok(());
+        }
 
-    Ok(())
+        let langs = detect_language()?;
+        match langs.len().cmp(&1) {
+            Ordering::Less => handle_none()?,
+            Ordering::Equal => build_one(langs.first().unwrap(), args)?,
+            Ordering::Greater => build_multiple(langs, args)?,
+        }
+
+        Ok(())
+    });
+
+    // Messages are taken even without targets, so they don't stay for later builds
+    let messages = take_messages();
+    if !targets.is_empty() && messages.is_none() && result.is_ok() {
+        bail!(
+            help = "Reports are written for cargo and C/C++ compilers",
+            "Build output wasn't parsed, so there is nothing to report"
+        );
+    }
+    let messages = messages.unwrap_or_default();
+    for target in &targets {
+        let written = target.write(&build_report(target.format, &messages, result.is_ok()));
+        match written {
+            Err(err) if result.is_ok() => return Err(err),
+            Err(err) => warn!("{:?}", err),
+            Ok(()) => {}
+        }
+    }
+    result
 }
 
 fn build_one(lang: &str, args: Build) -> miette::Result<()> {
-    let bind = PackageManagers::from_str(lang)?;
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
     let pkg = bind.to_manager()?;
 
     match bind {
@@ -29,14 +84,23 @@ fn build_one(lang: &str, args: Build) -> miette::Result<()> {
         | PackageManagers::Npm
         | PackageManagers::Yarn
         | PackageManagers::Pnpm
-        | PackageManagers::Cargo => build_from_manager(args, pkg),
-        PackageManagers::Gpp => build_from_binary(args, pkg),
+        | PackageManagers::Cargo
+        | PackageManagers::Scala
+        | PackageManagers::Swift => build_from_manager(args, pkg),
+        PackageManagers::Lua => build_from_rockspec(args, pkg),
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => build_native(args, pkg),
+        PackageManagers::Make | PackageManagers::Just => {
+            build_from_tasks(args, TaskRunner::from_manager(&bind).unwrap())
+        }
         _ => todo!(),
     }
 }
 
 pub fn build_from_manager(args: Build, pkg: Manager) -> miette::Result<()> {
-    if let Some(_) = args.name {
+    if args.name.is_some() {
         bail!("Invalid argument \"name\"");
         // if let Some(p) = pkg.build {
         //     let p = p.to_tuple()?;
@@ -46,8 +110,30 @@ pub fn build_from_manager(args: Build, pkg: Manager) -> miette::Result<()> {
         // }
     } else {
         if let Some(build) = pkg.build {
-            let p = build.to_tuple()?;
-            cmd!(p.0, p.1).run().into_diagnostic()?;
+            let profile = ResolvedProfile::load(
+                pkg.agent,
+                args.release,
+                args.debug,
+                args.profile.as_deref(),
+            )?;
+            let (program, mut p) = build.to_args()?;
+            p.extend(profile.args.iter().map(String::as_str));
+
+            // Cargo can't print JSON messages when user chose other format
+            let format = OutputFormat::for_manager(pkg.agent).filter(|v| {
+                *v != OutputFormat::CargoJson
+                    || !args.args.iter().any(|v| v.starts_with("--message-format"))
+            });
+            if format == Some(OutputFormat::CargoJson) {
+                p.push("--message-format=json");
+            }
+            pkg.push_args("build", &mut p, &args.args);
+
+            let expression = profile.apply(cmd(program, p));
+            match format {
+                Some(format) => run_parsed(expression, format)?,
+                None => run_child(expression)?,
+            }
         } else {
             bail!("Build script or executable file not found");
         }
@@ -56,26 +142,73 @@ pub fn build_from_manager(args: Build, pkg: Manager) -> miette::Result<()> {
     Ok(())
 }
 
-pub fn build_from_binary(args: Build, pkg: Manager) -> miette::Result<()> {
-    if let Some(name) = args.name {
-        let p = pkg.build.unwrap();
-        cmd!(format!("{}{}", p, name.display()))
-            .run()
-            .into_diagnostic()?;
-    } else {
-        if let Some(build) = pkg.build {
-            for x in build.split(" && ").collect::<Vec<_>>() {
-                let p = x.to_tuple()?;
-                cmd!(p.0, p.1).run().into_diagnostic()?;
-            }
-        } else {
-            bail!("Build script or executable file not found");
+pub fn build_native(args: Build, pkg: Manager) -> miette::Result<()> {
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    match args.name {
+        Some(name) if is_source_file(&name) => {
+            let output = build_file(&pkg, &profile, &name, &args.args, args.force)?;
+            info!("Binary saved to {}", output.display());
+        }
+        Some(_) => bail!("Invalid argument \"name\""),
+        None => {
+            build_project(&pkg, &profile, &args.args)?;
         }
     }
 
     Ok(())
 }
 
+pub fn build_from_rockspec(args: Build, pkg: Manager) -> miette::Result<()> {
+    if args.name.is_some() {
+        bail!("Invalid argument \"name\"");
+    }
+
+    if let Some(build) = pkg.build {
+        let rockspec = pick_rockspec()?;
+        let (program, mut p) = build.to_args()?;
+        let rockspec = rockspec.display().to_string();
+        p.push(&rockspec);
+        pkg.push_args("build", &mut p, &args.args);
+        run_child(cmd(program, p))?;
+    } else {
+        bail!("Build script or executable file not found");
+    }
+
+    Ok(())
+}
+
+/// sbt treats every argument as a separate command, so arguments of a task
+/// have to be passed together with it
+pub fn sbt_args(command: &str, args: &[String]) -> miette::Result<(String, Vec<String>)> {
+    let (program, p) = command.to_args()?;
+    let program = program.to_owned();
+    let mut p = p.into_iter().map(ToOwned::to_owned).collect::<Vec<_>>();
+    if let (Some(task), false) = (p.last_mut(), args.is_empty()) {
+        *task = format!("{} {}", task, args.join(" "));
+    }
+    Ok((program, p))
+}
+
+/// Finds rockspec in current directory and asks which one to use if there are more
+pub fn pick_rockspec() -> miette::Result<PathBuf> {
+    let mut rockspecs = Rockspec::find(Path::new("."))?;
+    match rockspecs.len().cmp(&1) {
+        Ordering::Less => bail!("No rockspec file was found"),
+        Ordering::Equal => Ok(rockspecs.remove(0)),
+        Ordering::Greater => {
+            let rockspecs = rockspecs
+                .iter()
+                .map(|v| v.display().to_string())
+                .collect::<Vec<_>>();
+            Select::new("What rockspec would you use?", rockspecs)
+                .prompt()
+                .map(PathBuf::from)
+                .into_diagnostic()
+        }
+    }
+}
+
 fn build_multiple(langs: Vec<String>, args: Build) -> miette::Result<()> {
     let manager = Select::new("What package manager would you use?", langs)
         .prompt()
@@ -95,6 +228,13 @@ mod tests {
 
     use std::env;
 
+    use ciri::cache_dir;
+    use ciri::entities::build_system::single_file_output;
+    use ciri::entities::export::parse_report_target;
+
+    use crate::commands::package::fingerprint::forget;
+    use crate::commands::package::native::project_steps;
+
     fn prepare_run_test(name: &str) -> anyhow::Result<()> {
         std::fs::create_dir_all(format!("/tmp/ciri/build_test/{}", name))?;
         cmd!(
@@ -126,6 +266,20 @@ mod tests {
         let res = build(Build::new(Some("example".into()), None, false));
         assert!(res.is_err());
 
+        let res = build(Build {
+            report: vec![
+                parse_report_target("json=reports/build.json").unwrap(),
+                parse_report_target("junit=reports/build.xml").unwrap(),
+            ],
+            ..Build::new(None, None, false)
+        });
+        assert!(res.is_ok());
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("reports/build.json")?)?;
+        assert_eq!(json["kind"], "build");
+        assert_eq!(json["success"], true);
+        assert!(std::fs::read_to_string("reports/build.xml")?.contains("<testcase name=\"build\""));
+
         clean("rust")?;
         Ok(())
     }
@@ -160,6 +314,206 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn build_c_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+
+        let res = build(Build::new(None, None, false));
+        assert!(res.is_ok());
+        assert!(std::path::Path::new("example").exists());
+
+        let res = build(Build::new(Some("main.c".into()), None, false));
+        assert!(res.is_ok());
+
+        let res = build(Build::new(Some("example".into()), None, false));
+        assert!(res.is_err());
+
+        clean("c")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_file_profile_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+
+        std::fs::write(
+            "profile.c",
+            "#ifndef FAST\n#error FAST isn't defined\n#endif\nint main() { return 0; }\n",
+        )?;
+        std::fs::write(
+            ".ciri.toml",
+            "[profiles.fast]\ninherits = \"release\"\nflags = [\"-DFAST\"]\n",
+        )?;
+        let mut args = Build::new(Some("profile.c".into()), None, false);
+        args.force = true;
+        assert!(build(args.clone()).is_err());
+
+        args.profile = Some("fast".to_owned());
+        assert!(build(args).is_ok());
+
+        clean("c")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_project_profile_test() -> anyhow::Result<()> {
+        prepare_run_test("cpp")?;
+
+        std::fs::write(
+            ".ciri.toml",
+            "[profiles.asan]\nflags = [\"-fsanitize=address\"]\n",
+        )?;
+        let pkg = PackageManagers::Gpp.to_manager().unwrap();
+        let profile = ResolvedProfile::load(pkg.agent, false, false, Some("asan")).unwrap();
+        let (_, _, steps) = project_steps(&pkg, &profile, &["-j4".to_owned()]).unwrap();
+        assert!(steps[0]
+            .args
+            .contains(&"-DCMAKE_CXX_FLAGS=-fsanitize=address".to_owned()));
+        assert_eq!(steps[1].args, ["--build", "build", "-j4"]);
+
+        clean("cpp")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_make_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+
+        let res = build(Build::new(None, None, false));
+        assert!(res.is_ok());
+        assert!(std::path::Path::new("out/result").exists());
+
+        let res = build(Build::new(Some("build".into()), None, false));
+        assert!(res.is_err());
+
+        clean("make")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_hooks_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+
+        std::fs::write(
+            ".ciri.toml",
+            r#"[hooks]
+pre_build = ["touch pre"]
+post_build = [{ task = "post" }]
+
+[tasks.post]
+command = "test -f out/result && touch post"
+"#,
+        )?;
+        let res = build(Build::new(None, None, false));
+        assert!(res.is_ok());
+        assert!(Path::new("pre").exists());
+        assert!(Path::new("post").exists());
+
+        std::fs::remove_dir_all("out")?;
+        std::fs::write(".ciri.toml", "[hooks]\npre_build = [\"exit 1\"]\n")?;
+        let err = build(Build::new(None, None, false)).unwrap_err();
+        assert_eq!(err.to_string(), "Hook \"pre_build\" failed");
+        assert!(!Path::new("out").exists());
+
+        clean("make")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_fingerprint_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+        forget().unwrap();
+
+        std::fs::create_dir_all("src")?;
+        std::fs::write("src/input.txt", "1")?;
+        std::fs::write(
+            ".ciri.toml",
+            r#"build_inputs = ["src/*.txt"]
+
+[hooks]
+pre_build = ["echo x >> generated"]
+
+[tasks.build]
+command = "echo x >> builds"
+"#,
+        )?;
+        let runs = || -> anyhow::Result<(usize, usize)> {
+            Ok((
+                std::fs::read_to_string("generated")?.lines().count(),
+                std::fs::read_to_string("builds")?.lines().count(),
+            ))
+        };
+
+        assert!(build(Build::new(None, None, false)).is_ok());
+        assert!(build(Build::new(None, None, false)).is_ok());
+        assert_eq!(runs()?, (1, 1));
+
+        std::fs::write("src/input.txt", "2")?;
+        assert!(build(Build::new(None, None, false)).is_ok());
+        assert_eq!(runs()?, (2, 2));
+
+        let force = Build {
+            force: true,
+            ..Build::new(None, None, false)
+        };
+        assert!(build(force).is_ok());
+        assert_eq!(runs()?, (3, 3));
+
+        // Changed commands aren't skipped even though inputs are the same
+        let config = std::fs::read_to_string(".ciri.toml")?;
+        std::fs::write(
+            ".ciri.toml",
+            config.replace("echo x >> generated", "echo y >> generated"),
+        )?;
+        assert!(build(Build::new(None, None, false)).is_ok());
+        assert_eq!(runs()?, (4, 3));
+        std::fs::write(
+            ".ciri.toml",
+            config.replace("echo x >> builds", "echo y >> builds"),
+        )?;
+        assert!(build(Build::new(None, None, false)).is_ok());
+        assert_eq!(runs()?, (5, 4));
+
+        forget().unwrap();
+        clean("make")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_file_fingerprint_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+        forget().unwrap();
+
+        let output =
+            single_file_output(&cache_dir().unwrap(), &Path::new("main.c").canonicalize()?);
+        let modified = || -> anyhow::Result<std::time::SystemTime> {
+            Ok(std::fs::metadata(&output)?.modified()?)
+        };
+
+        assert!(build(Build::new(Some("main.c".into()), None, false)).is_ok());
+        let first = modified()?;
+        assert!(build(Build::new(Some("main.c".into()), None, false)).is_ok());
+        assert_eq!(modified()?, first);
+
+        let force = Build {
+            force: true,
+            ..Build::new(Some("main.c".into()), None, false)
+        };
+        assert!(build(force).is_ok());
+        assert_ne!(modified()?, first);
+
+        forget().unwrap();
+        clean("c")?;
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn no_manager_test() {
@@ -1,24 +1,45 @@
 use std::cmp::Ordering;
+use std::path::Path;
 use std::str::FromStr;
 
+use ciri::args::package::Clean;
+use ciri::entities::build_system::{BuildSystem, CMakePresets};
+use ciri::entities::limits::ResourceLimits;
+use ciri::entities::managers::Manager;
+use ciri::entities::task_runner::TaskRunner;
 use ciri::validators::detect_language;
-use ciri::PackageManagers;
+use ciri::{Config, PackageManagers, Util};
+use duct::cmd;
 use inquire::Select;
 use miette::{bail, IntoDiagnostic};
 
-pub fn clean() -> miette::Result<()> {
-    let langs = detect_language()?;
-    match langs.len().cmp(&1) {
-        Ordering::Less => handle_none()?,
-        Ordering::Equal => clean_one(langs.get(0).unwrap())?,
-        Ordering::Greater => clean_multiple(langs)?,
-    }
+use super::fingerprint::forget;
+use super::native::{detect_build_system, run_step};
+use super::process::run_child;
+use super::task_runner::clean_from_tasks;
+use super::tasks::{run_override, with_hooks};
+
+pub fn clean(args: Clean) -> miette::Result<()> {
+    with_hooks("clean", || {
+        // Builds skipped because of unchanged inputs would leave nothing behind
+        forget()?;
+        if run_override("clean", &args.args, &ResourceLimits::default())? {
+            return Ok(());
+        }
+
+        let langs = detect_language()?;
+        match langs.len().cmp(&1) {
+            Ordering::Less => handle_none()?,
+            Ordering::Equal => clean_one(langs.first().unwrap(), args)?,
+            Ordering::Greater => clean_multiple(langs, args)?,
+        }
 
-    Ok(())
+        Ok(())
+    })
 }
 
-fn clean_one(lang: &str) -> miette::Result<()> {
-    let bind = PackageManagers::from_str(lang)?;
+fn clean_one(lang: &str, args: Clean) -> miette::Result<()> {
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
 
     let folder = match bind {
         PackageManagers::Bun
@@ -26,19 +47,84 @@ fn clean_one(lang: &str) -> miette::Result<()> {
         | PackageManagers::Yarn
         | PackageManagers::Pnpm => "node_modules",
         PackageManagers::Cargo => "target",
-        PackageManagers::Gpp => "build",
+        PackageManagers::Scala | PackageManagers::Swift => {
+            return clean_from_manager(args, bind.to_manager()?)
+        }
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => return clean_native(args),
+        PackageManagers::Make | PackageManagers::Just => {
+            return clean_from_tasks(args, TaskRunner::from_manager(&bind).unwrap())
+        }
         _ => todo!(),
     };
 
+    if !args.args.is_empty() {
+        warn!("Extra arguments are ignored when removing \"{}\"", folder);
+    }
+
     std::fs::remove_dir_all(format!("./{}", folder)).into_diagnostic()?;
     Ok(())
 }
 
-fn clean_multiple(langs: Vec<String>) -> miette::Result<()> {
+fn clean_native(args: Clean) -> miette::Result<()> {
+    let system = detect_build_system()?;
+    // Link to compile commands from removed build directory would be left dangling
+    let link = Path::new("compile_commands.json");
+    if link.is_symlink() {
+        std::fs::remove_file(link).into_diagnostic()?;
+    }
+
+    let mut dirs = system
+        .output_dirs()
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>();
+    if system == BuildSystem::CMake {
+        if let Some(presets) = CMakePresets::read(Path::new("."))? {
+            dirs.extend(
+                presets
+                    .visible()
+                    .iter()
+                    .filter_map(|v| presets.binary_dir(v)),
+            );
+        }
+    }
+
+    for dir in dirs.iter().filter(|v| Path::new(v).exists()) {
+        std::fs::remove_dir_all(dir).into_diagnostic()?;
+    }
+
+    // In-tree builds only know themselves what to remove
+    if let Some(mut step) = system.clean_step() {
+        if system != BuildSystem::Autotools || Path::new("Makefile").exists() {
+            step.args.extend(args.args);
+            run_step(&step)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cleans using manager's own command for managers that keep build files in many places
+fn clean_from_manager(args: Clean, pkg: Manager) -> miette::Result<()> {
+    if let Some(clean) = pkg.clean {
+        let (program, mut p) = clean.to_args()?;
+        pkg.push_args("clean", &mut p, &args.args);
+        run_child(cmd(program, p))?;
+    } else {
+        bail!("Clean command not found");
+    }
+
+    Ok(())
+}
+
+fn clean_multiple(langs: Vec<String>, args: Clean) -> miette::Result<()> {
     let manager = Select::new("What package manager would you use?", langs)
         .prompt()
         .into_diagnostic()?;
-    clean_one(manager.as_str())
+    clean_one(manager.as_str(), args)
 }
 
 fn handle_none() -> miette::Result<()> {
@@ -50,11 +136,10 @@ fn handle_none() -> miette::Result<()> {
 mod tests {
 
     use crate::commands::package::build;
-    use ciri::args::package::Build;
+    use ciri::args::package::{Build, Clean};
 
     use super::*;
 
-    use duct::cmd;
     use std::env;
 
     fn prepare_run_test(name: &str) -> anyhow::Result<()> {
@@ -82,13 +167,8 @@ mod tests {
     fn clean_rust_test() -> anyhow::Result<()> {
         prepare_run_test("rust")?;
 
-        build(Build {
-            name: None,
-            script: None,
-            watch: false,
-        })
-        .unwrap();
-        let res = clean();
+        build(Build::new(None, None, false)).unwrap();
+        let res = clean(Clean::default());
         assert!(res.is_ok());
 
         clean_test("rust")?;
@@ -103,7 +183,7 @@ mod tests {
         // Imitate installing packages
         std::fs::create_dir("node_modules")?;
 
-        let res = clean();
+        let res = clean(Clean::default());
         assert!(res.is_ok());
 
         clean_test("node")?;
@@ -115,25 +195,48 @@ mod tests {
     fn clean_cpp_test() -> anyhow::Result<()> {
         prepare_run_test("cpp")?;
 
-        build(Build {
-            name: None,
-            script: None,
-            watch: false,
-        })
-        .unwrap();
-        let res = clean();
+        build(Build::new(None, None, false)).unwrap();
+        let res = clean(Clean::default());
         assert!(res.is_ok());
 
         clean_test("cpp")?;
         Ok(())
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn clean_c_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+
+        build(Build::new(None, None, false)).unwrap();
+        let res = clean(Clean::default());
+        assert!(res.is_ok());
+        assert!(!std::path::Path::new("example").exists());
+
+        clean_test("c")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn clean_make_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+
+        build(Build::new(None, None, false)).unwrap();
+        let res = clean(Clean::default());
+        assert!(res.is_ok());
+        assert!(!std::path::Path::new("out").exists());
+
+        clean_test("make")?;
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn no_manager_test() {
         prepare_run_test("").unwrap();
 
-        let res = clean();
+        let res = clean(Clean::default());
         assert!(res.is_ok());
     }
 }
@@ -0,0 +1,25 @@
+use std::env;
+use std::path::Path;
+
+use ciri::entities::env::{load_env, masked};
+use ciri::Config;
+
+/// Loads variables from `.env` files and config so every child process inherits them
+pub fn apply_env(profile: Option<&str>, print: bool) -> miette::Result<()> {
+    let config = Config::load()?.env.unwrap_or_default();
+    let vars = load_env(Path::new("."), profile, &config, |name| env::var(name).ok())?;
+
+    if print {
+        if vars.is_empty() {
+            info!("No variables were loaded");
+        }
+        for (key, value) in &vars {
+            info!("{}={}", key, masked(key, value));
+        }
+    }
+
+    for (key, value) in vars {
+        env::set_var(key, value);
+    }
+    Ok(())
+}
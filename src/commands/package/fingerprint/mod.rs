@@ -0,0 +1,51 @@
+use std::env;
+
+use ciri::entities::fingerprint::{default_inputs, fingerprint, Fingerprints};
+use ciri::validators::detect_language;
+use ciri::Config;
+use miette::IntoDiagnostic;
+
+/// Runs build step unless its inputs are the same as after its last success, returns whether it ran
+///
+/// Fingerprint is taken after the step, so files it generates into inputs don't cause rebuilds.
+/// Steps without inputs always run
+pub fn unless_fresh(
+    step: &str,
+    inputs: &[String],
+    extra: &[String],
+    force: bool,
+    f: impl FnOnce() -> miette::Result<()>,
+) -> miette::Result<bool> {
+    if inputs.is_empty() {
+        f()?;
+        return Ok(true);
+    }
+
+    let project = env::current_dir().into_diagnostic()?;
+    let mut fingerprints = Fingerprints::load(&project)?;
+    if !force && fingerprints.is_fresh(step, &fingerprint(&project, inputs, extra)?) {
+        return Ok(false);
+    }
+
+    f()?;
+    fingerprints.save(step, fingerprint(&project, inputs, extra)?)?;
+    Ok(true)
+}
+
+/// Inputs from `build_inputs` of config or defaults of detected managers
+pub fn project_inputs(config: &Config) -> miette::Result<Vec<String>> {
+    if let Some(inputs) = &config.build_inputs {
+        return Ok(inputs.clone());
+    }
+
+    Ok(detect_language()?
+        .iter()
+        .flat_map(|v| default_inputs(v))
+        .map(|v| v.to_string())
+        .collect())
+}
+
+/// Forgets fingerprints of current project, so next builds run again
+pub fn forget() -> miette::Result<()> {
+    Fingerprints::load(&env::current_dir().into_diagnostic()?)?.clear()
+}
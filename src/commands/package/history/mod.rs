@@ -0,0 +1,132 @@
+use std::env;
+
+use ciri::args::package::History as HistoryArgs;
+use ciri::entities::history::{format_timestamp, trends, History, HistoryEntry, Trend};
+use miette::IntoDiagnostic;
+use ratatui::layout::Constraint;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::Cell;
+
+use crate::components::table::{run_app, Table};
+use crate::components::{finalize_app, prepare_app};
+
+/// Change from previous run that is colored as slower or faster
+const NOTABLE_CHANGE: f64 = 0.1;
+
+pub fn history(args: HistoryArgs) -> miette::Result<()> {
+    let entries = History::open()?.entries()?;
+    let project = env::current_dir().into_diagnostic()?;
+
+    let rows = rows(&entries, &trends(&entries), |entry| {
+        args.all || entry.project == project
+    });
+    if rows.is_empty() {
+        info!("No builds, runs or tests were recorded yet");
+        return Ok(());
+    }
+
+    let headers = vec![
+        "Time", "Project", "Manager", "Command", "Duration", "Change", "Trend", "Exit",
+    ];
+    let widths = vec![
+        Constraint::Percentage(13),
+        Constraint::Percentage(30),
+        Constraint::Percentage(10),
+        Constraint::Percentage(8),
+        Constraint::Percentage(10),
+        Constraint::Percentage(9),
+        Constraint::Percentage(12),
+        Constraint::Percentage(8),
+    ];
+
+    let app = Table::new(
+        "Build History",
+        None,
+        headers,
+        rows,
+        widths,
+        Some(|cell, multiline_cell, j| match j {
+            5 if cell.starts_with('+') => {
+                Cell::from(Line::styled(multiline_cell, Style::default().red()))
+            }
+            5 if cell.starts_with('-') => {
+                Cell::from(Line::styled(multiline_cell, Style::default().green()))
+            }
+            7 if cell != "0" => Cell::from(multiline_cell).red(),
+            _ => Cell::from(multiline_cell),
+        }),
+    );
+
+    let mut terminal = prepare_app()?;
+    run_app(app, &mut terminal)?;
+    finalize_app(terminal)?;
+
+    Ok(())
+}
+
+/// Table rows from newest entry. Change is signed only when it's notable, so it can be colored
+fn rows(
+    entries: &[HistoryEntry],
+    trends: &[Trend],
+    filter: impl Fn(&HistoryEntry) -> bool,
+) -> Vec<Vec<String>> {
+    entries
+        .iter()
+        .zip(trends)
+        .rev()
+        .filter(|(entry, _)| filter(entry))
+        .map(|(entry, trend)| {
+            let change = match trend.change {
+                Some(v) if v.abs() >= NOTABLE_CHANGE => format!("{:+.0}%", v * 100.0),
+                Some(v) => format!("{:.0}%", v.abs() * 100.0),
+                None => String::new(),
+            };
+            vec![
+                format_timestamp(entry.timestamp),
+                entry.project.display().to_string(),
+                entry.manager.clone(),
+                entry.command.clone(),
+                format!("{:.2}s", entry.duration),
+                change,
+                trend.sparkline(),
+                entry.exit_code.to_string(),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn rows_test() {
+        let entry = |project: &str, duration: f64| HistoryEntry {
+            project: PathBuf::from(project),
+            manager: "cargo".to_owned(),
+            command: "build".to_owned(),
+            duration,
+            exit_code: 0,
+            timestamp: 0,
+        };
+        let entries = vec![
+            entry("/a", 2.0),
+            entry("/b", 1.0),
+            entry("/a", 3.0),
+            entry("/a", 3.1),
+        ];
+
+        let rows = rows(&entries, &trends(&entries), |v| {
+            v.project == Path::new("/a")
+        });
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][4], "3.10s");
+        assert_eq!(rows[0][5], "3%");
+        assert_eq!(rows[1][5], "+50%");
+        assert_eq!(rows[2][5], "");
+        assert_eq!(rows[2][0], "1970-01-01 00:00");
+    }
+}
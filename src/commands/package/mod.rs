@@ -1,9 +1,30 @@
+mod add;
 mod build;
 mod clean;
+mod env;
+mod fingerprint;
+mod history;
+mod native;
 mod new;
+mod process;
+mod remove;
 mod run;
+mod start;
+mod summary;
+mod task_runner;
+mod tasks;
+mod test;
+mod update;
 
+pub use add::add;
 pub use build::build;
 pub use clean::clean;
+pub use history::history;
 pub use new::new;
+pub use process::{exit_code, ChildExit};
+pub use remove::remove;
 pub use run::run;
+pub use start::start;
+pub use summary::with_summary;
+pub use test::test;
+pub use update::update;
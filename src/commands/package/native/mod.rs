@@ -0,0 +1,342 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ciri::entities::build_system::{
+    compile_file, single_file_output, BuildOptions, BuildSystem, BuildType, CMakePresets, Step,
+};
+use ciri::entities::cmake_file_api::{self, CMakeTarget};
+use ciri::entities::fingerprint::literal_input;
+use ciri::entities::limits::ResourceLimits;
+use ciri::entities::managers::Manager;
+use ciri::entities::profile::ResolvedProfile;
+use ciri::parsers::compiler::OutputFormat;
+use ciri::{cache_dir, Config};
+use clap::builder::OsStr;
+use duct::cmd;
+use inquire::Select;
+use miette::{miette, IntoDiagnostic, WrapErr};
+
+use super::fingerprint::unless_fresh;
+use super::process::{record_messages, run_limited, run_parsed};
+use super::tasks::with_build_hooks;
+use super::test::run_tests;
+
+pub fn run_step(step: &Step) -> miette::Result<()> {
+    run_step_limited(step, &ResourceLimits::default())
+}
+
+pub fn run_step_limited(step: &Step, limits: &ResourceLimits) -> miette::Result<()> {
+    run_limited(step_expression(step), limits)
+        .wrap_err(format!("Command \"{}\" failed", step.program))
+}
+
+/// Runs step that compiles code, errors of compiler are shown as diagnostics
+pub fn build_step(step: &Step) -> miette::Result<()> {
+    run_parsed(step_expression(step), OutputFormat::Gcc)
+        .wrap_err(format!("Command \"{}\" failed", step.program))
+}
+
+fn step_expression(step: &Step) -> duct::Expression {
+    let mut expression = cmd(&step.program, &step.args);
+    for (key, value) in &step.env {
+        expression = expression.env(key, value);
+    }
+    expression
+}
+
+pub fn detect_build_system() -> miette::Result<BuildSystem> {
+    BuildSystem::detect(Path::new(".")).ok_or_else(|| {
+        miette!("No supported build system (CMake, Meson, Autotools, Make) was found")
+    })
+}
+
+/// Resolves how project in current directory should be configured
+pub fn build_options(
+    pkg: &Manager,
+    build_type: Option<BuildType>,
+) -> miette::Result<(BuildSystem, BuildOptions)> {
+    let system = detect_build_system()?;
+    let mut options = BuildOptions {
+        compiler: pkg.agent.to_owned(),
+        build_dir: system.build_dir().to_owned(),
+        build_type,
+        ..Default::default()
+    };
+
+    if system == BuildSystem::CMake {
+        if let Some(presets) = CMakePresets::read(Path::new("."))? {
+            if let Some(preset) = pick_preset(&presets, build_type)? {
+                if let Some(binary_dir) = presets.binary_dir(&preset) {
+                    options.build_dir = binary_dir;
+                }
+                options.build_preset = presets.build_preset(&preset);
+                options.test_preset = presets.test_preset(&preset);
+                options.preset = Some(preset);
+            }
+        }
+
+        // Presets choose their own generator and toolchain
+        if options.preset.is_none() {
+            if which::which("ninja").is_ok() {
+                options.generator = Some("Ninja".to_owned());
+            }
+            options.toolchain_file = vcpkg_toolchain();
+        }
+    }
+
+    options.configured = system.is_configured(Path::new(&options.build_dir));
+    options.bootstrap = system.bootstrap_step(Path::new("."));
+    Ok((system, options))
+}
+
+/// Toolchain file of vcpkg for projects using manifest mode
+fn vcpkg_toolchain() -> Option<String> {
+    if !Path::new("vcpkg.json").exists() {
+        return None;
+    }
+
+    let root = env::var_os("VCPKG_ROOT").map(PathBuf::from).or_else(|| {
+        let vcpkg = which::which("vcpkg").ok()?.canonicalize().ok()?;
+        vcpkg.parent().map(Path::to_path_buf)
+    });
+    let toolchain = root.map(|v| v.join("scripts/buildsystems/vcpkg.cmake"));
+    match toolchain {
+        Some(toolchain) if toolchain.exists() => Some(toolchain.display().to_string()),
+        _ => {
+            warn!("vcpkg.json was found but vcpkg wasn't, set VCPKG_ROOT to use dependencies");
+            None
+        }
+    }
+}
+
+/// Picks configure preset, preferring ones named after requested build type
+fn pick_preset(
+    presets: &CMakePresets,
+    build_type: Option<BuildType>,
+) -> miette::Result<Option<String>> {
+    let mut visible = presets.visible();
+    if let Some(build_type) = build_type {
+        let build_type = build_type.to_string().to_lowercase();
+        let matching = visible
+            .iter()
+            .filter(|v| v.to_lowercase().contains(&build_type))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !matching.is_empty() {
+            visible = matching;
+        }
+    }
+
+    match visible.len() {
+        0 => Ok(None),
+        1 => Ok(visible.pop()),
+        _ => Select::new("What preset would you use?", visible)
+            .prompt()
+            .map(Some)
+            .into_diagnostic(),
+    }
+}
+
+/// Configures and builds project in current directory with manager's compiler
+///
+/// Profile flags are passed to compiler when configuring, extra arguments to the final build step
+pub fn build_project(
+    pkg: &Manager,
+    profile: &ResolvedProfile,
+    args: &[String],
+) -> miette::Result<(BuildSystem, BuildOptions)> {
+    let (system, options, steps) = project_steps(pkg, profile, args)?;
+    if system == BuildSystem::CMake {
+        cmake_file_api::write_query(Path::new(&options.build_dir))?;
+    }
+    for step in steps {
+        build_step(&step)?;
+    }
+
+    link_compile_commands(&options.build_dir)?;
+    Ok((system, options))
+}
+
+/// Steps configuring and building project with profile applied
+pub fn project_steps(
+    pkg: &Manager,
+    profile: &ResolvedProfile,
+    args: &[String],
+) -> miette::Result<(BuildSystem, BuildOptions, Vec<Step>)> {
+    let (system, mut options) = build_options(pkg, profile.build_type)?;
+    options.flags = profile.args.clone();
+
+    let mut steps = system.build_steps(&options);
+    if let Some(step) = steps.last_mut() {
+        step.args.extend(args.iter().cloned());
+    }
+    for step in steps.iter_mut() {
+        step.env.extend(profile.env.iter().cloned());
+    }
+    Ok((system, options, steps))
+}
+
+/// Links `compile_commands.json` from build directory to project root so language servers can find it
+fn link_compile_commands(build_dir: &str) -> miette::Result<()> {
+    let target = Path::new(build_dir).join("compile_commands.json");
+    let link = Path::new("compile_commands.json");
+    if !target.exists() || target == link {
+        return Ok(());
+    }
+
+    if let Ok(metadata) = fs::symlink_metadata(link) {
+        if !metadata.file_type().is_symlink() {
+            warn!("compile_commands.json already exists and isn't a link, skipping");
+            return Ok(());
+        }
+        fs::remove_file(link).into_diagnostic()?;
+    }
+
+    std::os::unix::fs::symlink(&target, link)
+        .into_diagnostic()
+        .wrap_err("Failed to link compile_commands.json")
+}
+
+/// Builds project between build hooks and runs its tests, limits apply only to tests
+pub fn test_project(
+    pkg: &Manager,
+    profile: &ResolvedProfile,
+    limits: &ResourceLimits,
+    filter: Option<&str>,
+    args: &[String],
+) -> miette::Result<()> {
+    let (system, options) = with_build_hooks(false, || build_project(pkg, profile, &[]))?;
+    let mut steps = system.test_steps(&options, filter);
+    if steps.is_empty() {
+        return Err(miette!("Tests aren't supported by this build system"));
+    }
+
+    if let Some(step) = steps.last_mut() {
+        step.args.extend(args.iter().cloned());
+    }
+    for mut step in steps {
+        step.env.extend(profile.env.iter().cloned());
+        run_tests(step_expression(&step), limits)
+            .wrap_err(format!("Command \"{}\" failed", step.program))?;
+    }
+
+    Ok(())
+}
+
+/// Compiles single source file into cache directory and returns path to binary
+///
+/// Flags of profile's build type and then profile flags and extra arguments are added to
+/// compiler flags from config
+pub fn build_file(
+    pkg: &Manager,
+    profile: &ResolvedProfile,
+    source: &Path,
+    args: &[String],
+    force: bool,
+) -> miette::Result<PathBuf> {
+    let config = Config::load()?;
+    let mut flags = if pkg.agent.ends_with("++") {
+        config.cxxflags
+    } else {
+        config.cflags
+    }
+    .unwrap_or_default();
+    if let Some(build_type) = profile.build_type {
+        flags.extend(
+            build_type
+                .optimization_flags()
+                .split_whitespace()
+                .map(str::to_owned),
+        );
+    }
+    flags.extend(profile.args.iter().cloned());
+    flags.extend(args.iter().cloned());
+
+    let source = source
+        .canonicalize()
+        .into_diagnostic()
+        .wrap_err(format!("File \"{}\" wasn't found", source.display()))?;
+    let output = single_file_output(&cache_dir()?, &source);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).into_diagnostic()?;
+    }
+
+    // Headers of single files aren't known, they can be listed in build_inputs
+    let mut inputs = vec![literal_input(&source)];
+    inputs.extend(config.build_inputs.unwrap_or_default());
+    let ldflags = config.ldflags.unwrap_or_default();
+    let mut extra = vec![pkg.agent.to_owned()];
+    extra.extend(flags.iter().chain(&ldflags).cloned());
+
+    let step = format!("file:{}", output.display());
+    let force = force || !output.exists();
+    let built = unless_fresh(&step, &inputs, &extra, force, || {
+        let mut step = compile_file(pkg.agent, &source, &output, &flags, &ldflags);
+        step.env.extend(profile.env.iter().cloned());
+        build_step(&step)
+    })?;
+    if !built {
+        record_messages(&[]);
+        info!("{} didn't change, skipping build", source.display());
+    }
+    Ok(output)
+}
+
+/// Path to executable produced by build system
+///
+/// CMake projects are asked for their executable targets, if there are several user picks one.
+/// Otherwise falls back to `bin_name` from config or name of current directory
+pub fn project_executable(
+    system: BuildSystem,
+    options: &BuildOptions,
+    name: Option<String>,
+) -> miette::Result<PathBuf> {
+    if system == BuildSystem::CMake {
+        let config = options.build_type.map(|v| v.to_string());
+        let targets =
+            cmake_file_api::executables(Path::new(&options.build_dir), config.as_deref())?;
+        if let Some(mut targets) = targets.filter(|v| !v.is_empty()) {
+            if let Some(name) = &name {
+                if let Some(target) = targets.iter().find(|v| &v.name == name) {
+                    return Ok(target.artifact.clone());
+                }
+            } else {
+                return pick_target(&mut targets);
+            }
+        }
+    }
+
+    let name = if let Some(name) = name {
+        name
+    } else if let Some(bin_name) = Config::load()?.bin_name {
+        bin_name
+    } else {
+        let current_dir = env::current_dir().into_diagnostic()?;
+        let str = OsStr::from("");
+        current_dir
+            .file_name()
+            .unwrap_or(&str)
+            .to_str()
+            .unwrap_or("")
+            .to_owned()
+    };
+
+    Ok(Path::new(&options.build_dir).join(name))
+}
+
+fn pick_target(targets: &mut Vec<CMakeTarget>) -> miette::Result<PathBuf> {
+    if targets.len() == 1 {
+        return Ok(targets.remove(0).artifact);
+    }
+
+    let names = targets.iter().map(|v| v.name.clone()).collect::<Vec<_>>();
+    let name = Select::new("What target would you run?", names)
+        .prompt()
+        .into_diagnostic()?;
+    Ok(targets
+        .iter()
+        .find(|v| v.name == name)
+        .map(|v| v.artifact.clone())
+        .unwrap_or_default())
+}
@@ -1,14 +1,18 @@
 use std::fs::{self, File};
 use std::io::Write;
+use std::path::Path;
 
 use ciri::args::package::New;
+use ciri::entities::manifest::VcpkgManifest;
 use ciri::Config;
 use duct::cmd;
+use inquire::Confirm;
 use miette::IntoDiagnostic;
 
 use super::{prompt_name, prompt_type};
 
 pub fn new(args: New) -> miette::Result<()> {
+    let compiler = args.manager.to_string();
     let name = if let Some(new_name) = args.name {
         new_name
     } else {
@@ -24,15 +28,31 @@ pub fn new(args: New) -> miette::Result<()> {
         "src"
     };
 
-    create_structure(&name, folder)?;
+    let vcpkg = if args.defaults {
+        false
+    } else {
+        Confirm::new("Would you like to manage dependencies with vcpkg?")
+            .with_default(false)
+            .prompt()
+            .into_diagnostic()?
+    };
+
+    create_structure(&name, folder, &compiler)?;
     create_files(&name, folder)?;
+    if vcpkg {
+        VcpkgManifest::new(&name).save(&Path::new(&name).join("vcpkg.json"))?;
+    }
     Ok(())
 }
 
-fn create_structure(name: &str, folder: &str) -> miette::Result<()> {
+fn create_structure(name: &str, folder: &str, compiler: &str) -> miette::Result<()> {
     fs::create_dir_all(format!("{}/{}", name, folder)).into_diagnostic()?;
     fs::create_dir(format!("{}/include", name)).into_diagnostic()?;
-    let config = Config::new(Some(name.to_owned()), None);
+    // Compiler is picked at configure time, so the one chosen here is remembered in config
+    let config = Config {
+        compiler: Some(compiler.to_owned()),
+        ..Config::new(Some(name.to_owned()), None)
+    };
     config.save(Some(name))?;
     Ok(())
 }
@@ -0,0 +1,70 @@
+use std::fs::{self, File};
+use std::io::Write;
+
+use ciri::args::package::New;
+use ciri::entities::manifest::Rockspec;
+use ciri::Config;
+use duct::cmd;
+use miette::IntoDiagnostic;
+
+use super::{prompt_description, prompt_license, prompt_name, prompt_type, prompt_version};
+
+pub fn new(args: New) -> miette::Result<()> {
+    let manager = args.manager.to_string();
+    let name = prompt_name(args.name)?;
+
+    let (version, summary, license, _type) = if args.defaults {
+        ("1.0.0".to_owned(), "".to_owned(), "MIT".to_owned(), "bin")
+    } else {
+        (
+            prompt_version()?,
+            prompt_description()?,
+            prompt_license()?,
+            prompt_type()?,
+        )
+    };
+
+    // Rockspec versions always end with revision of the rockspec itself
+    let mut rockspec = Rockspec::new(name.clone(), format!("{}-1", version), summary, license);
+    let entry = if _type == "bin" {
+        rockspec.bin = vec![(name.clone(), "src/main.lua".to_owned())];
+        "src/main.lua".to_owned()
+    } else {
+        let module = format!("src/{}.lua", name);
+        rockspec.modules = vec![(name.clone(), module.clone())];
+        module
+    };
+
+    fs::create_dir_all(format!("{}/src", name)).into_diagnostic()?;
+    File::create(format!("{}/{}", name, rockspec.file_name()))
+        .into_diagnostic()?
+        .write_all(rockspec.to_string().as_bytes())
+        .into_diagnostic()?;
+
+    let content = if _type == "bin" {
+        "print(\"Hello, World!\")\n".to_owned()
+    } else {
+        "local M = {}\n\nfunction M.hello()\n  return \"Hello, World!\"\nend\n\nreturn M\n"
+            .to_owned()
+    };
+    File::create(format!("{}/{}", name, entry))
+        .into_diagnostic()?
+        .write_all(content.as_bytes())
+        .into_diagnostic()?;
+
+    File::create(format!("{}/.gitignore", name))
+        .into_diagnostic()?
+        .write_all(b"*.rock")
+        .into_diagnostic()?;
+
+    let config = Config::new(Some(name.clone()), Some(manager));
+    config.save(Some(&name))?;
+
+    cmd!("git", "init", &name)
+        .stdout_null()
+        .stderr_null()
+        .run()
+        .into_diagnostic()?;
+
+    Ok(())
+}
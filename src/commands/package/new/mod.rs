@@ -4,16 +4,19 @@ use ciri::args::package::New;
 use ciri::validators::inquire::{
     license_validator, lowercase_validator, url_friendliness_validator, version_validator,
 };
-use ciri::PackageManagers;
+use ciri::{Config, PackageManagers};
 use clap::builder::OsStr;
 use inquire::{required, Select, Text};
 use miette::IntoDiagnostic;
 
 mod cargo;
 mod cpp;
+mod lua;
 mod node;
 
-pub fn new(args: New) -> miette::Result<()> {
+pub fn new(mut args: New) -> miette::Result<()> {
+    // `-m` picks the language, `compiler` from config can pick clang instead of gcc for it
+    args.manager = args.manager.with_config(&Config::load()?);
     match args.manager {
         PackageManagers::Npm | PackageManagers::Pnpm | PackageManagers::Yarn => {
             node::new(args)?;
@@ -22,6 +25,7 @@ pub fn new(args: New) -> miette::Result<()> {
         PackageManagers::Bun => node::new_bun(args),
         PackageManagers::Cargo => cargo::new(args),
         PackageManagers::Gpp | PackageManagers::Clangpp => cpp::new(args),
+        PackageManagers::Lua => lua::new(args),
         _ => todo!(),
     }
 }
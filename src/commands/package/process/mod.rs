@@ -0,0 +1,539 @@
+use std::cell::RefCell;
+use std::env;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use ciri::entities::limits::{format_size, ResourceLimits};
+use ciri::parsers::compiler::{CompilerMessage, Level, MessageParser, OutputFormat};
+use duct::Expression;
+use miette::{miette, Diagnostic, IntoDiagnostic, WrapErr};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
+use signal_hook::iterator::Signals;
+use signal_hook::low_level::emulate_default_handler;
+
+/// Signals received by ciri that are passed to running children
+pub const FORWARDED_SIGNALS: [i32; 4] = [SIGINT, SIGTERM, SIGHUP, SIGQUIT];
+
+/// Child process that didn't exit successfully, ciri exits with the same code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildExit {
+    pub code: i32,
+    /// Signal that killed the child
+    pub signal: Option<i32>,
+}
+
+impl ChildExit {
+    /// Exit code like the one reported by shells, 128 + signal for killed processes
+    pub fn from_status(status: ExitStatus) -> Option<Self> {
+        if status.success() {
+            return None;
+        }
+
+        Some(match (status.code(), status.signal()) {
+            (Some(code), _) => Self { code, signal: None },
+            (None, Some(signal)) => Self {
+                code: 128 + signal,
+                signal: Some(signal),
+            },
+            (None, None) => Self {
+                code: 1,
+                signal: None,
+            },
+        })
+    }
+
+    /// Child was stopped on purpose, e.g. with Ctrl-C, not killed by a crash
+    pub fn interrupted(&self) -> bool {
+        self.signal.is_some_and(|v| FORWARDED_SIGNALS.contains(&v))
+    }
+}
+
+impl Display for ChildExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.signal {
+            Some(signal) => write!(f, "Process was killed by signal {}", signal),
+            None => write!(f, "Process exited with code {}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for ChildExit {}
+
+impl Diagnostic for ChildExit {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Timeout(Duration),
+    CpuTime(Duration),
+    Memory(u64),
+}
+
+/// Child process that was stopped by one of [`ResourceLimits`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub exit: ChildExit,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.limit {
+            Limit::Timeout(v) => write!(f, "Process didn't finish in {:?} and was killed", v),
+            Limit::CpuTime(v) => write!(f, "Process used more than {:?} of CPU time", v),
+            Limit::Memory(v) => write!(
+                f,
+                "{}, possibly because its memory was limited to {}",
+                self.exit,
+                format_size(v)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl Diagnostic for LimitExceeded {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match self.limit {
+            Limit::Timeout(_) => "ciri::limits::timeout",
+            Limit::CpuTime(_) => "ciri::limits::cpu_time",
+            Limit::Memory(_) => "ciri::limits::memory",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help = match self.limit {
+            Limit::Timeout(_) => "Raise it with --timeout or timeout in [limits] of .ciri.toml",
+            Limit::CpuTime(_) => "Raise it with --cpu-time or cpu_time in [limits] of .ciri.toml",
+            Limit::Memory(_) => {
+                "Raise it with --memory-limit or memory_limit in [limits] of .ciri.toml"
+            }
+        };
+        Some(Box::new(help))
+    }
+}
+
+/// Runs command in its own process group until it exits
+///
+/// Signals received by ciri are forwarded to the whole group, so processes started by
+/// the child don't outlive it. Unsuccessful exit is returned as [`ChildExit`]
+pub fn run_child(expression: Expression) -> miette::Result<()> {
+    run_limited(expression, &ResourceLimits::default())
+}
+
+/// Runs command like [`run_child`] with rlimits set, whole group is killed when limit is hit
+pub fn run_limited(expression: Expression, limits: &ResourceLimits) -> miette::Result<()> {
+    let handle = in_group(expression, limits).start().into_diagnostic()?;
+    supervise(handle.pids()[0] as i32, limits, || {
+        handle.wait().map(|output| output.status)
+    })
+}
+
+thread_local! {
+    /// Compiler messages of builds run by current invocation, written by `--report`
+    ///
+    /// None when output of no build was parsed
+    static MESSAGES: RefCell<Option<Vec<CompilerMessage>>> = const { RefCell::new(None) };
+}
+
+/// Takes messages of every build run by [`run_parsed`] so far
+pub fn take_messages() -> Option<Vec<CompilerMessage>> {
+    MESSAGES.with(|v| v.take())
+}
+
+/// Adds messages of build, skipped builds add none but they still count as parsed
+pub fn record_messages(messages: &[CompilerMessage]) {
+    MESSAGES.with(|v| {
+        v.borrow_mut()
+            .get_or_insert_with(Vec::new)
+            .extend(messages.iter().cloned())
+    });
+}
+
+/// Runs command like [`run_child`], but messages of compiler are rendered again as diagnostics
+///
+/// Other output is printed to stderr as it comes, so stdout stays free for `--json`
+pub fn run_parsed(expression: Expression, format: OutputFormat) -> miette::Result<()> {
+    let mut parser = MessageParser::new(format);
+    let result = run_lines(expression, &ResourceLimits::default(), |line| {
+        for passed in parser.push(line) {
+            eprintln!("{}", passed);
+        }
+    });
+
+    let (rest, messages) = parser.finish();
+    for line in rest {
+        eprintln!("{}", line);
+    }
+    let root = env::current_dir().into_diagnostic()?;
+    for message in &messages {
+        eprintln!("{:?}", message.to_report(&root));
+    }
+    record_messages(&messages);
+
+    let errors = messages.iter().filter(|v| v.level == Level::Error).count();
+    match errors {
+        0 => result,
+        1 => result.wrap_err("Build failed with 1 error"),
+        n => result.wrap_err(format!("Build failed with {} errors", n)),
+    }
+}
+
+/// Runs command like [`run_limited`] and passes lines of its stdout and stderr to `on_line`
+pub fn run_lines(
+    expression: Expression,
+    limits: &ResourceLimits,
+    mut on_line: impl FnMut(&str),
+) -> miette::Result<()> {
+    let reader = in_group(expression.stderr_to_stdout(), limits)
+        .reader()
+        .into_diagnostic()?;
+    supervise(reader.pids()[0] as i32, limits, || {
+        let mut lines = BufReader::new(&reader);
+        let mut line = vec![];
+        while lines.read_until(b'\n', &mut line)? > 0 {
+            let text = String::from_utf8_lossy(&line);
+            on_line(text.trim_end_matches(['\n', '\r']));
+            line.clear();
+        }
+        // Reader waits for the child when output ends
+        match reader.try_wait()? {
+            Some(output) => Ok(output.status),
+            None => Err(std::io::Error::other("Process didn't exit")),
+        }
+    })
+}
+
+/// Expression that starts in its own process group with rlimits set
+fn in_group(expression: Expression, limits: &ResourceLimits) -> Expression {
+    let rlimits = *limits;
+    expression.unchecked().before_spawn(move |command| {
+        command.process_group(0);
+        // SAFETY: setrlimit is async-signal-safe and doesn't allocate
+        unsafe {
+            command.pre_exec(move || set_rlimits(&rlimits));
+        }
+        Ok(())
+    })
+}
+
+/// Waits for started group while forwarding signals to it and enforcing timeout
+fn supervise(
+    pgid: i32,
+    limits: &ResourceLimits,
+    wait: impl FnOnce() -> std::io::Result<ExitStatus>,
+) -> miette::Result<()> {
+    let (timed_out, stop_watchdog) = watchdog(pgid, limits.timeout);
+
+    let foreground = Foreground::give(pgid);
+    let forwarding = Forwarding::start(pgid)?;
+
+    let status = wait();
+    drop(stop_watchdog);
+    drop(forwarding);
+    drop(foreground);
+
+    let exit = ChildExit::from_status(status.into_diagnostic()?);
+    // Child could handle SIGTERM of watchdog and still exit successfully
+    let limit = if timed_out.load(Ordering::SeqCst) {
+        limits.timeout.map(Limit::Timeout)
+    } else {
+        match exit.as_ref().and_then(|v| v.signal) {
+            Some(libc::SIGXCPU) => limits.cpu_time.map(Limit::CpuTime),
+            // Failed allocations usually end with abort or segfault
+            Some(libc::SIGABRT | libc::SIGSEGV | libc::SIGBUS) => limits.memory.map(Limit::Memory),
+            _ => None,
+        }
+    };
+
+    match (limit, exit) {
+        (None, None) => Ok(()),
+        (None, Some(exit)) => Err(exit.into()),
+        (Some(limit), exit) => {
+            // Processes started by the child could still be running
+            signal_group(pgid, libc::SIGKILL);
+            let exit = match limit {
+                // Same code as coreutils timeout
+                Limit::Timeout(_) => ChildExit {
+                    code: 124,
+                    signal: None,
+                },
+                _ => exit.unwrap_or(ChildExit {
+                    code: 1,
+                    signal: None,
+                }),
+            };
+            Err(LimitExceeded { limit, exit }.into())
+        }
+    }
+}
+
+fn set_rlimits(limits: &ResourceLimits) -> std::io::Result<()> {
+    let set = |resource, soft: u64, hard: u64| {
+        let limit = libc::rlimit {
+            rlim_cur: soft,
+            rlim_max: hard,
+        };
+        // SAFETY: rlimit is a valid struct for the duration of the call
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    };
+
+    if let Some(memory) = limits.memory {
+        set(libc::RLIMIT_DATA, memory, memory)?;
+    }
+    if let Some(cpu_time) = limits.cpu_time {
+        // Soft limit sends SIGXCPU, hard one a second later kills
+        let seconds = cpu_time.as_secs_f64().ceil().max(1.0) as u64;
+        set(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+    }
+    Ok(())
+}
+
+/// Terminates group when timeout passes, dropping returned sender stops it
+fn watchdog(pgid: i32, timeout: Option<Duration>) -> (Arc<AtomicBool>, mpsc::Sender<()>) {
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let (stop, stopped) = mpsc::channel::<()>();
+    if let Some(timeout) = timeout {
+        let timed_out = timed_out.clone();
+        thread::spawn(move || {
+            if stopped.recv_timeout(timeout) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return;
+            }
+            timed_out.store(true, Ordering::SeqCst);
+            signal_group(pgid, libc::SIGTERM);
+            if stopped.recv_timeout(KILL_TIMEOUT) == Err(mpsc::RecvTimeoutError::Timeout) {
+                signal_group(pgid, libc::SIGKILL);
+            }
+        });
+    }
+    (timed_out, stop)
+}
+
+/// Code ciri exits with after error, the child's one when it failed
+pub fn exit_code(err: &miette::Report) -> i32 {
+    if let Some(exceeded) = err.downcast_ref::<LimitExceeded>() {
+        return exceeded.exit.code;
+    }
+    err.downcast_ref::<ChildExit>().map_or(1, |exit| exit.code)
+}
+
+/// Time processes get to exit after SIGTERM before they are killed
+pub const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends signal to every process in group
+pub fn signal_group(pgid: i32, signal: i32) {
+    // SAFETY: kill only sends a signal, negative pid targets the whole group
+    unsafe { libc::kill(-pgid, signal) };
+}
+
+/// Group started elsewhere that gets signals of ciri, and its terminal when it's in foreground
+pub struct Attached {
+    _forwarding: Forwarding,
+    _foreground: Option<Foreground>,
+}
+
+/// Attaches group until returned value is dropped, like groups run by [`run_child`]
+pub fn attach(pgid: i32, foreground: bool) -> miette::Result<Attached> {
+    Ok(Attached {
+        _foreground: foreground.then(|| Foreground::give(pgid)),
+        _forwarding: Forwarding::start(pgid)?,
+    })
+}
+
+/// Groups of running children, signals received by ciri are forwarded to them
+static GROUPS: Mutex<Vec<i32>> = Mutex::new(vec![]);
+
+/// Group registered for forwarding of signals until it's dropped
+struct Forwarding {
+    pgid: i32,
+}
+
+impl Forwarding {
+    fn start(pgid: i32) -> miette::Result<Self> {
+        // Handlers can't be unregistered without losing default behavior, so they are
+        // registered once and ciri behaves like without them while no child runs
+        static FORWARDER: OnceLock<Result<(), String>> = OnceLock::new();
+        FORWARDER
+            .get_or_init(|| {
+                let mut signals = Signals::new(FORWARDED_SIGNALS).map_err(|e| e.to_string())?;
+                thread::spawn(move || {
+                    for signal in signals.forever() {
+                        let groups = GROUPS.lock().map(|v| v.clone()).unwrap_or_default();
+                        if groups.is_empty() {
+                            let _ = emulate_default_handler(signal);
+                        }
+                        for pgid in groups {
+                            signal_group(pgid, signal);
+                        }
+                    }
+                });
+                Ok(())
+            })
+            .clone()
+            .map_err(|e| miette!("Signals can't be forwarded to child: {}", e))?;
+
+        if let Ok(mut groups) = GROUPS.lock() {
+            groups.push(pgid);
+        }
+        Ok(Self { pgid })
+    }
+}
+
+impl Drop for Forwarding {
+    fn drop(&mut self) {
+        if let Ok(mut groups) = GROUPS.lock() {
+            groups.retain(|v| *v != self.pgid);
+        }
+    }
+}
+
+/// Terminal handed over to child's group, so it gets Ctrl-C and can read input
+struct Foreground {
+    previous: Option<libc::pid_t>,
+}
+
+impl Foreground {
+    fn give(pgid: i32) -> Self {
+        // SAFETY: these calls only query and change foreground group of terminal
+        unsafe {
+            let own = libc::getpgrp();
+            if libc::isatty(libc::STDIN_FILENO) == 0 || libc::tcgetpgrp(libc::STDIN_FILENO) != own {
+                return Self { previous: None };
+            }
+
+            // Background group changing terminal settings would be stopped otherwise
+            libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+            // Child could have been stopped reading input before it got the terminal
+            signal_group(pgid, libc::SIGCONT);
+            Self {
+                previous: Some(own),
+            }
+        }
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            // SAFETY: gives terminal back to ciri's own group
+            unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, previous) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use duct::cmd;
+    use miette::WrapErr;
+
+    fn exit_of(script: &str) -> Option<ChildExit> {
+        run_child(cmd!("sh", "-c", script))
+            .err()
+            .map(|err| err.downcast_ref::<ChildExit>().unwrap().clone())
+    }
+
+    #[test]
+    fn run_child_test() {
+        assert_eq!(exit_of("true"), None);
+        assert_eq!(
+            exit_of("exit 3"),
+            Some(ChildExit {
+                code: 3,
+                signal: None
+            })
+        );
+        assert_eq!(
+            exit_of("kill -TERM $$"),
+            Some(ChildExit {
+                code: 143,
+                signal: Some(15)
+            })
+        );
+        assert!(exit_of("kill -TERM $$").unwrap().interrupted());
+        assert!(!exit_of("kill -SEGV $$").unwrap().interrupted());
+        assert!(!exit_of("exit 130").unwrap().interrupted());
+
+        // Exit is found behind context added by callers
+        let err = run_child(cmd!("false"))
+            .wrap_err("Hook failed")
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<ChildExit>().map(|v| v.code), Some(1));
+        assert_eq!(exit_code(&err), 1);
+        assert_eq!(exit_code(&miette::miette!("Not a child")), 1);
+        assert_eq!(
+            exit_code(&run_child(cmd!("sh", "-c", "exit 7")).unwrap_err()),
+            7
+        );
+    }
+
+    #[test]
+    fn run_parsed_test() {
+        let script = "echo 'x.c:1:1: error: bad'; echo other; exit 2";
+        let err = run_parsed(cmd!("sh", "-c", script), OutputFormat::Gcc).unwrap_err();
+        assert_eq!(err.to_string(), "Build failed with 1 error");
+        assert_eq!(exit_code(&err), 2);
+        assert_eq!(take_messages().unwrap()[0].message, "bad");
+        assert!(take_messages().is_none());
+
+        assert!(run_parsed(cmd!("sh", "-c", "echo other"), OutputFormat::Gcc).is_ok());
+    }
+
+    fn limit_of(script: &str, limits: ResourceLimits) -> Option<Limit> {
+        run_limited(cmd!("sh", "-c", script), &limits)
+            .err()
+            .and_then(|err| err.downcast_ref::<LimitExceeded>().map(|v| v.limit))
+    }
+
+    #[test]
+    fn run_limited_test() -> anyhow::Result<()> {
+        let timeout = ResourceLimits {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        assert_eq!(
+            limit_of("sleep 10", timeout),
+            Some(Limit::Timeout(Duration::from_millis(200)))
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(limit_of("true", timeout), None);
+        // Exiting successfully after SIGTERM of watchdog is still a timeout
+        assert_eq!(
+            limit_of("trap 'exit 0' TERM; sleep 10", timeout),
+            Some(Limit::Timeout(Duration::from_millis(200)))
+        );
+
+        let cpu_time = ResourceLimits {
+            cpu_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            limit_of("while :; do :; done", cpu_time),
+            Some(Limit::CpuTime(Duration::from_secs(1)))
+        );
+
+        let memory = ResourceLimits {
+            memory: Some(64 * 1024 * 1024),
+            ..Default::default()
+        };
+        let path = "/tmp/ciri/run_limited_test";
+        std::fs::create_dir_all("/tmp/ciri")?;
+        assert_eq!(limit_of(&format!("ulimit -d > {}", path), memory), None);
+        assert_eq!(std::fs::read_to_string(path)?.trim(), "65536");
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
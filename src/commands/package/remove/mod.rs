@@ -0,0 +1,64 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use ciri::args::package::Remove;
+use ciri::validators::detect_language;
+use ciri::{Config, PackageManagers, Util};
+use duct::cmd;
+use inquire::Select;
+use miette::{bail, IntoDiagnostic};
+
+pub fn remove(args: Remove) -> miette::Result<()> {
+    let langs = detect_language()?;
+    match langs.len().cmp(&1) {
+        Ordering::Less => bail!("No valid package manager was detected"),
+        Ordering::Equal => remove_one(langs.first().unwrap(), args),
+        Ordering::Greater => {
+            let manager = Select::new("What package manager would you use?", langs)
+                .prompt()
+                .into_diagnostic()?;
+            remove_one(&manager, args)
+        }
+    }
+}
+
+fn remove_one(lang: &str, args: Remove) -> miette::Result<()> {
+    let pkg = PackageManagers::from_str(lang)?
+        .with_config(&Config::load()?)
+        .to_manager()?;
+    if pkg.remove.is_empty() {
+        bail!("Dependencies can't be removed from {} projects", pkg.agent);
+    }
+
+    let (program, mut p) = pkg.remove.to_args()?;
+    p.push(&args.name);
+    pkg.push_args("remove", &mut p, &args.args);
+    cmd(program, p).run().into_diagnostic()?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    #[serial_test::serial]
+    fn remove_make_test() -> anyhow::Result<()> {
+        let _ = std::fs::remove_dir_all("/tmp/ciri/remove_test");
+        std::fs::create_dir_all("/tmp/ciri/remove_test")?;
+        env::set_current_dir("/tmp/ciri/remove_test")?;
+        std::fs::write("Makefile", "all:\n\ttrue\n")?;
+
+        let res = remove(Remove::new("foo".to_owned()));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Dependencies can't be removed from make projects"
+        );
+
+        std::fs::remove_dir_all("/tmp/ciri/remove_test")?;
+        Ok(())
+    }
+}
@@ -1,33 +1,65 @@
 use std::cmp::Ordering;
-use std::env;
-// use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use ciri::args::package::{Build, Run};
+use ciri::entities::build_system::is_source_file;
 use ciri::entities::managers::Manager;
+use ciri::entities::manifest::{Rockspec, SwiftPackage};
+use ciri::entities::profile::ResolvedProfile;
+use ciri::entities::task_runner::TaskRunner;
 // use ciri::entities::manifest::PackageJson;
 use ciri::validators::detect_language;
 use ciri::{Config, PackageManagers, Util};
-use clap::builder::OsStr;
 use duct::cmd;
 use inquire::Select;
 use miette::{bail, Context, IntoDiagnostic};
 
-use super::build::{build_from_binary, build_from_manager};
+use super::build::{build_from_manager, build_from_rockspec, sbt_args};
+use super::env::apply_env;
+use super::native::{build_file, build_options, build_project, project_executable};
+use super::process::run_limited;
+use super::task_runner::run_from_tasks;
+use super::tasks::{run_override, with_build_hooks, with_hooks};
 
 pub fn run(args: Run) -> miette::Result<()> {
-    let langs = detect_language()?;
-    match langs.len().cmp(&1) {
-        Ordering::Less => handle_none()?,
-        Ordering::Equal => run_one(langs.get(0).unwrap(), args)?,
-        Ordering::Greater => run_multiple(langs, args)?,
-    }
+    apply_env(args.env_profile.as_deref(), args.print_env)?;
+
+    // Tasks from config take precedence over targets and scripts of the same name
+    let task = args
+        .name
+        .as_ref()
+        .map_or("run".to_owned(), |v| v.display().to_string());
+    let limits = args.limits()?;
+    with_hooks("run", || {
+        if run_override(&task, &args.args, &limits)? {
+            return Ok(());
+        }
 
-    Ok(())
+        let langs = detect_language()?;
+        match langs.len().cmp(&1) {
+            Ordering::Less => handle_none()?,
+            Ordering::Equal => run_one(langs.first().unwrap(), args)?,
+            Ordering::Greater => run_multiple(langs, args)?,
+        }
+
+        Ok(())
+    })
+}
+
+/// Arguments of build run before executing, it uses the same profile
+fn build_args(args: &Run, name: Option<PathBuf>) -> Build {
+    Build {
+        release: args.release,
+        debug: args.debug,
+        profile: args.profile.clone(),
+        force: args.force,
+        ..Build::new(name, None, args.watch)
+    }
 }
 
 fn run_one(lang: &str, args: Run) -> miette::Result<()> {
-    let bind = PackageManagers::from_str(lang)?;
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
     let pkg = bind.to_manager()?;
 
     match bind {
@@ -37,15 +69,46 @@ fn run_one(lang: &str, args: Run) -> miette::Result<()> {
         | PackageManagers::Pnpm
         | PackageManagers::Cargo => {
             if args.build {
-                build_from_manager(Build::new(args.name.clone(), None, args.watch), pkg.clone())?;
+                with_build_hooks(args.force, || {
+                    build_from_manager(build_args(&args, args.name.clone()), pkg.clone())
+                })?;
             }
             run_from_manager(args, pkg)
         }
-        PackageManagers::Gpp => {
+        PackageManagers::Lua => {
+            if args.build {
+                with_build_hooks(args.force, || {
+                    build_from_rockspec(build_args(&args, None), pkg.clone())
+                })?;
+            }
+            run_from_entry(args, pkg)
+        }
+        PackageManagers::Scala => {
+            if args.build {
+                with_build_hooks(args.force, || {
+                    build_from_manager(build_args(&args, None), pkg.clone())
+                })?;
+            }
+            if pkg.agent == "sbt" {
+                run_from_sbt(args, pkg)
+            } else {
+                run_from_manager(args, pkg)
+            }
+        }
+        PackageManagers::Swift => {
             if args.build {
-                build_from_binary(Build::new(None, None, args.watch), pkg.clone())?;
+                with_build_hooks(args.force, || {
+                    build_from_manager(build_args(&args, None), pkg.clone())
+                })?;
             }
-            run_from_binary(args, pkg)
+            run_from_swift(args, pkg)
+        }
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => run_native(args, pkg),
+        PackageManagers::Make | PackageManagers::Just => {
+            run_from_tasks(args, TaskRunner::from_manager(&bind).unwrap())
         }
         _ => todo!(),
     }
@@ -53,18 +116,31 @@ fn run_one(lang: &str, args: Run) -> miette::Result<()> {
 
 // TODO: Typescript files only work with bun right now because it has native support
 fn run_from_manager(args: Run, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
     if let Some(name) = args.name {
         let p = pkg.run.unwrap();
-        let p = p.to_tuple()?;
-        cmd!(p.0, p.1, name).run().into_diagnostic()?;
+        let (program, mut p) = p.to_args()?;
+        p.extend(profile.args.iter().map(String::as_str));
+        let name = name.display().to_string();
+        p.push(&name);
+        pkg.push_args("run", &mut p, &args.args);
+        run_limited(profile.apply(cmd(program, p)), &limits)?;
     } else {
         if let Some(default_exec) = pkg.default_exec {
-            let p = pkg.default_exec.unwrap();
-            if let Ok(tuple) = p.to_tuple() {
-                cmd!(tuple.0, tuple.1).run().into_diagnostic()?;
+            let (program, mut p) = default_exec.to_args()?;
+            p.extend(profile.args.iter().map(String::as_str));
+            if p.is_empty() {
+                p.push(".");
+            }
+            // Separator is meant for the manager, programs like node would get it as argument
+            if program == pkg.agent {
+                pkg.push_args("run", &mut p, &args.args);
             } else {
-                cmd!(default_exec, ".").run().into_diagnostic()?;
+                p.extend(args.args.iter().map(String::as_str));
             }
+            run_limited(profile.apply(cmd(program, p)), &limits)?;
         } else {
             bail!("Run script or executable file not found");
         }
@@ -84,43 +160,151 @@ fn run_from_manager(args: Run, pkg: Manager) -> miette::Result<()> {
 //     Ok(())
 // }
 
-fn run_from_binary(args: Run, pkg: Manager) -> miette::Result<()> {
-    if let Some(name) = args.name {
-        let p = pkg.run.unwrap();
-        cmd!(format!("{}{}", p, name.display()))
-            .run()
-            .into_diagnostic()?;
+fn run_from_sbt(args: Run, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let task = if args.name.is_some() {
+        pkg.run
     } else {
-        if let Some(default_exec) = pkg.default_exec {
-            let config = Config::read()?;
-
-            let current_dir = env::current_dir().into_diagnostic()?;
-            let str = OsStr::from("");
-            let current_dir = current_dir
-                .file_name()
-                .unwrap_or(&str)
-                .to_str()
-                .unwrap_or("");
-
-            cmd!(format!(
-                "{}{}",
-                default_exec,
-                config.bin_name.unwrap_or(current_dir.to_owned())
-            ))
-            .run()
-            .into_diagnostic()
-            .wrap_err(
-                "Check if project was build successfully or update/set bin_name in \".ciri.toml\"",
-            )
-            .wrap_err("Executable wasn't found.")?;
-        } else {
-            bail!("Run script or executable file not found");
+        pkg.default_exec
+    };
+    let Some(task) = task else {
+        bail!("Run script or executable file not found");
+    };
+
+    let mut task_args = args
+        .name
+        .map(|v| vec![v.display().to_string()])
+        .unwrap_or_default();
+    task_args.extend(args.args);
+    let (program, p) = sbt_args(task, &task_args)?;
+    run_limited(cmd(program, p), &limits)?;
+    Ok(())
+}
+
+/// Runs single source file or executable of C/C++ project
+fn run_native(args: Run, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    let executable = match args.name {
+        // Single files are always compiled before running
+        Some(name) if is_source_file(&name) => with_build_hooks(args.force, || {
+            build_file(&pkg, &profile, &name, &[], args.force)
+        })?,
+        name => {
+            let (system, options) = if args.build {
+                with_build_hooks(args.force, || build_project(&pkg, &profile, &[]))?
+            } else {
+                build_options(&pkg, profile.build_type)?
+            };
+            project_executable(system, &options, name.map(|v| v.display().to_string()))?
         }
+    };
+
+    if !executable.exists() {
+        bail!(
+            help =
+                "Check if project was build successfully or update/set bin_name in \".ciri.toml\"",
+            "Executable {} wasn't found.",
+            executable.display()
+        );
+    }
+    run_limited(profile.apply(cmd(&executable, &args.args)), &limits)
+}
+
+fn run_from_swift(args: Run, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(run) = pkg.run else {
+        bail!("Run script or executable file not found");
+    };
+
+    let product = if let Some(name) = args.name {
+        name.display().to_string()
+    } else {
+        pick_swift_product()?
+    };
+
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    let (program, mut p) = run.to_args()?;
+    p.extend(profile.args.iter().map(String::as_str));
+    p.push(&product);
+    pkg.push_args("run", &mut p, &args.args);
+    run_limited(profile.apply(cmd(program, p)), &limits)?;
+    Ok(())
+}
+
+/// Lists executable products from package manifest and asks which one to run
+fn pick_swift_product() -> miette::Result<String> {
+    let manifest = cmd!("swift", "package", "dump-package")
+        .stderr_null()
+        .read()
+        .into_diagnostic()
+        .wrap_err("Couldn't read Package.swift")?;
+    let mut executables = SwiftPackage::from_str(&manifest)?.executables();
+
+    match executables.len().cmp(&1) {
+        Ordering::Less => bail!("Package doesn't have any executable products"),
+        Ordering::Equal => Ok(executables.remove(0)),
+        Ordering::Greater => Select::new("What product would you run?", executables)
+            .prompt()
+            .into_diagnostic(),
     }
+}
+
+fn run_from_entry(args: Run, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(exec) = pkg.run else {
+        bail!("Run script or executable file not found");
+    };
+
+    let script = if let Some(name) = args.name {
+        name
+    } else {
+        pick_lua_entry()?
+    };
 
+    let mut p = vec![script.display().to_string()];
+    p.extend(args.args);
+    run_limited(cmd(exec, p), &limits)?;
     Ok(())
 }
 
+/// Finds entry script from rockspec `install.bin` or commonly used file names
+fn pick_lua_entry() -> miette::Result<PathBuf> {
+    let mut entries = Rockspec::find(Path::new("."))?
+        .into_iter()
+        .filter_map(|path| match Rockspec::try_from(path.clone()) {
+            Ok(rockspec) => Some(rockspec),
+            Err(err) => {
+                warn!("Skipping {}: {}", path.display(), err);
+                None
+            }
+        })
+        .flat_map(|rockspec| rockspec.bin.into_iter().map(|(_, script)| script))
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        entries = ["main.lua", "init.lua", "src/main.lua", "src/init.lua"]
+            .iter()
+            .filter(|v| Path::new(v).exists())
+            .map(|v| v.to_string())
+            .collect();
+    }
+
+    // Several rockspecs, e.g. of different versions, often list the same scripts
+    entries.sort();
+    entries.dedup();
+    match entries.len().cmp(&1) {
+        Ordering::Less => bail!("Entry script wasn't found"),
+        Ordering::Equal => Ok(PathBuf::from(entries.remove(0))),
+        Ordering::Greater => Select::new("What script would you run?", entries)
+            .prompt()
+            .map(PathBuf::from)
+            .into_diagnostic(),
+    }
+}
+
 fn run_multiple(langs: Vec<String>, args: Run) -> miette::Result<()> {
     let config = Config::read()?;
     let manager = if let Some(manager) = config.prefered_project_manager {
@@ -207,8 +391,26 @@ mod tests {
         Ok(())
     }
 
-    // NOTE: This test will fail for now because of incorect
-    // binary name for cpp.
+    #[test]
+    #[serial_test::serial]
+    fn run_node_args_test() -> anyhow::Result<()> {
+        prepare_run_test("node")?;
+
+        std::fs::write(
+            "index.js",
+            "require('fs').writeFileSync('args', JSON.stringify(process.argv.slice(2)));\n",
+        )?;
+        let args = Run {
+            args: vec!["--port".to_owned(), "8080".to_owned()],
+            ..Run::new(None, false, false)
+        };
+        assert!(run(args).is_ok());
+        assert_eq!(std::fs::read_to_string("args")?, r#"["--port","8080"]"#);
+
+        clean("node")?;
+        Ok(())
+    }
+
     #[test]
     #[serial_test::serial]
     fn run_cpp_test() -> anyhow::Result<()> {
@@ -217,8 +419,9 @@ mod tests {
         let res = run(Run::new(None, false, false));
         assert!(res.is_err());
 
+        // Only target is found through CMake file API
         let res = run(Run::new(None, true, false));
-        assert!(res.is_err());
+        assert!(res.is_ok());
 
         let res = run(Run::new(Some("example".into()), false, false));
         assert!(res.is_ok());
@@ -230,6 +433,98 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn run_c_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+
+        let res = run(Run::new(None, false, false));
+        assert!(res.is_err());
+
+        let res = run(Run::new(None, true, false));
+        assert!(res.is_ok());
+
+        let res = run(Run::new(Some("main.c".into()), false, false));
+        assert!(res.is_ok());
+
+        let res = run(Run::new(Some("missing.c".into()), false, false));
+        assert!(res.is_err());
+
+        clean("c")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn run_file_hooks_test() -> anyhow::Result<()> {
+        prepare_run_test("c")?;
+
+        std::fs::write(
+            ".ciri.toml",
+            "[hooks]\npre_build = [\"echo x >> pre\"]\npost_build = [\"touch post\"]\n",
+        )?;
+        let res = run(Run::new(Some("main.c".into()), false, false));
+        assert!(res.is_ok());
+        assert!(Path::new("post").exists());
+
+        // Without build_inputs hooks run before every build
+        let res = run(Run::new(Some("main.c".into()), false, false));
+        assert!(res.is_ok());
+        assert_eq!(std::fs::read_to_string("pre")?, "x\nx\n");
+
+        clean("c")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn run_args_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+
+        std::fs::write(
+            ".ciri.toml",
+            "[tasks.args]\ncommand = \"printf '%s|' > args\"\n",
+        )?;
+        let mut args = Run::new(Some("args".into()), false, false);
+        args.args = vec!["--port".to_owned(), "it's 8080".to_owned()];
+        let res = run(args);
+        assert!(res.is_ok());
+        assert_eq!(std::fs::read_to_string("args")?, "--port|it's 8080|");
+
+        clean("make")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn run_env_test() -> anyhow::Result<()> {
+        prepare_run_test("make")?;
+
+        std::fs::write(".env", "CIRI_ENV_HOST=localhost\n")?;
+        std::fs::write(
+            ".env.staging",
+            "CIRI_ENV_URL=http://${CIRI_ENV_HOST}:8080\n",
+        )?;
+        std::fs::write(
+            ".ciri.toml",
+            "[tasks.env]\ncommand = \"echo $CIRI_ENV_URL > env\"\n",
+        )?;
+        let mut args = Run::new(Some("env".into()), false, false);
+        args.env_profile = Some("staging".to_owned());
+        let res = run(args);
+        assert!(res.is_ok());
+        assert_eq!(std::fs::read_to_string("env")?, "http://localhost:8080\n");
+
+        let mut args = Run::new(Some("env".into()), false, false);
+        args.env_profile = Some("missing".to_owned());
+        assert!(run(args).is_err());
+
+        env::remove_var("CIRI_ENV_HOST");
+        env::remove_var("CIRI_ENV_URL");
+        clean("make")?;
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn no_manager_test() {
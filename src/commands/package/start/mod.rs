@@ -0,0 +1,243 @@
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use ciri::args::package::Start;
+use ciri::entities::processes::{load_processes, Process};
+use ciri::Config;
+use crossterm::style::Color;
+use miette::{bail, miette, IntoDiagnostic};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+use super::env::apply_env;
+use super::process::{signal_group, KILL_TIMEOUT};
+use crate::colorize_string;
+
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Green,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+pub fn start(args: Start) -> miette::Result<()> {
+    apply_env(args.env_profile.as_deref(), false)?;
+
+    let mut processes = load_processes(Path::new("."), &Config::load()?)?;
+    if let Some(name) = args
+        .names
+        .iter()
+        .find(|name| !processes.iter().any(|(v, _)| v == *name))
+    {
+        bail!("Process \"{}\" isn't defined", name);
+    }
+    if !args.names.is_empty() {
+        processes.retain(|(name, _)| args.names.contains(name));
+    }
+
+    if let Some(restart) = &args.restart_on_exit {
+        for (name, process) in processes.iter_mut() {
+            if restart.is_empty() || restart.contains(name) {
+                process.restart_on_exit = Some(true);
+            }
+        }
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .into_diagnostic()?
+        .block_on(supervise(processes))
+}
+
+/// Runs processes until Ctrl-C or until one of them exits without being restarted
+async fn supervise(processes: Vec<(String, Process)>) -> miette::Result<()> {
+    let width = processes.iter().map(|(v, _)| v.len()).max().unwrap_or(0);
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    let mut running = JoinSet::new();
+    for (i, (name, process)) in processes.into_iter().enumerate() {
+        let prefix = colorize_string(&format!("{:width$} |", name), COLORS[i % COLORS.len()]);
+        running.spawn(keep_running(name, prefix, process, shutdown_rx.clone()));
+    }
+
+    let mut result = Ok(());
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Shutting down"),
+        exited = running.join_next() => {
+            if let Some(exited) = exited {
+                result = exit_result(exited.into_diagnostic()?);
+            }
+        }
+    }
+
+    let _ = shutdown.send(true);
+    while let Some(exited) = running.join_next().await {
+        exited.into_diagnostic()??;
+    }
+    result
+}
+
+fn exit_result(exited: miette::Result<(String, Option<ExitStatus>)>) -> miette::Result<()> {
+    match exited? {
+        (name, Some(status)) if !status.success() => {
+            Err(miette!("Process \"{}\" exited with {}", name, status))
+        }
+        (name, Some(status)) => {
+            info!(
+                "Process \"{}\" exited with {}, stopping others",
+                name, status
+            );
+            Ok(())
+        }
+        (_, None) => Ok(()),
+    }
+}
+
+/// Runs process, restarting it when configured. Status is `None` when it was stopped
+async fn keep_running(
+    name: String,
+    prefix: String,
+    process: Process,
+    mut shutdown: watch::Receiver<bool>,
+) -> miette::Result<(String, Option<ExitStatus>)> {
+    loop {
+        let mut child = spawn(&process)?;
+        let mut output = JoinSet::new();
+        if let Some(stdout) = child.stdout.take() {
+            output.spawn(print_lines(prefix.clone(), stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            output.spawn(print_lines(prefix.clone(), stderr));
+        }
+
+        let status = tokio::select! {
+            status = child.wait() => status.into_diagnostic()?,
+            _ = shutdown.changed() => {
+                terminate(&mut child).await?;
+                while output.join_next().await.is_some() {}
+                return Ok((name, None));
+            }
+        };
+        while output.join_next().await.is_some() {}
+
+        if process.restart_on_exit != Some(true) || *shutdown.borrow() {
+            return Ok((name, Some(status)));
+        }
+        println!("{} exited with {}, restarting", prefix, status);
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            _ = shutdown.changed() => return Ok((name, Some(status))),
+        }
+    }
+}
+
+fn spawn(process: &Process) -> miette::Result<Child> {
+    let mut command = std::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&process.command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Own group lets us stop everything the process started
+        .process_group(0);
+    if let Some(cwd) = &process.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(env) = &process.env {
+        command.envs(env);
+    }
+    Command::from(command)
+        .kill_on_drop(true)
+        .spawn()
+        .into_diagnostic()
+}
+
+async fn print_lines(prefix: String, stream: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        println!("{} {}", prefix, line);
+    }
+}
+
+/// Sends SIGTERM to process group and kills it when it doesn't exit in time
+async fn terminate(child: &mut Child) -> miette::Result<()> {
+    let Some(pid) = child.id() else {
+        return Ok(());
+    };
+
+    signal_group(pid as i32, libc::SIGTERM);
+    if tokio::time::timeout(KILL_TIMEOUT, child.wait())
+        .await
+        .is_err()
+    {
+        signal_group(pid as i32, libc::SIGKILL);
+        child.wait().await.into_diagnostic()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::time::Instant;
+
+    fn prepare_start_test(procfile: &str) -> anyhow::Result<()> {
+        let _ = std::fs::remove_dir_all("/tmp/ciri/start_test");
+        std::fs::create_dir_all("/tmp/ciri/start_test")?;
+        env::set_current_dir("/tmp/ciri/start_test")?;
+        std::fs::write("Procfile", procfile)?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn start_test() -> anyhow::Result<()> {
+        prepare_start_test("short: echo done > short\nlong: sleep 30\n")?;
+
+        // Exit of one process stops the other one
+        let started = Instant::now();
+        let res = start(Start::default());
+        assert!(res.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(std::fs::read_to_string("short")?, "done\n");
+
+        let res = start(Start {
+            names: vec!["missing".to_owned()],
+            ..Default::default()
+        });
+        assert!(res.is_err());
+
+        std::fs::remove_dir_all("/tmp/ciri/start_test")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn start_restart_test() -> anyhow::Result<()> {
+        // Restarts happen a second apart, so counter runs twice before stopper exits
+        prepare_start_test("counter: echo x >> runs\nstopper: sleep 1.5; exit 3\n")?;
+
+        let res = start(Start {
+            restart_on_exit: Some(vec!["counter".to_owned()]),
+            ..Default::default()
+        });
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Process \"stopper\" exited with exit status: 3"
+        );
+        assert_eq!(std::fs::read_to_string("runs")?, "x\nx\n");
+
+        std::fs::remove_dir_all("/tmp/ciri/start_test")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::time::Instant;
+
+use ciri::entities::history::{History, HistoryEntry};
+use ciri::entities::summary::{ChildUsage, Summary};
+use ciri::validators::detect_language;
+use miette::IntoDiagnostic;
+
+use super::process::exit_code;
+
+/// Runs subcommand, then prints how long it took and what its child processes used
+///
+/// Invocation is also recorded to history shown by `ciri history`
+pub fn with_summary(
+    command: &str,
+    json: bool,
+    f: impl FnOnce() -> miette::Result<()>,
+) -> miette::Result<()> {
+    let project = env::current_dir().into_diagnostic()?;
+    let manager = detect_language().unwrap_or_default().join(", ");
+    let before = ChildUsage::now();
+    let start = Instant::now();
+    let result = f();
+    let code = result.as_ref().err().map_or(0, exit_code);
+    let summary = Summary::new(command, start.elapsed(), before, ChildUsage::now(), code);
+
+    let entry = HistoryEntry::new(project, manager, command, summary.wall_time, code);
+    if let Err(err) = History::open().and_then(|v| v.record(&entry)) {
+        warn!("History wasn't recorded: {}", err);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&summary).into_diagnostic()?);
+    } else {
+        info!("{}", summary);
+    }
+    result
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    use duct::cmd;
+
+    use crate::commands::package::process::run_child;
+
+    #[test]
+    #[serial_test::serial]
+    fn with_summary_test() -> anyhow::Result<()> {
+        let _ = std::fs::remove_dir_all("/tmp/ciri/summary_test");
+        std::fs::create_dir_all("/tmp/ciri/summary_test")?;
+        env::set_current_dir("/tmp/ciri/summary_test")?;
+        let state = env::var_os("XDG_STATE_HOME");
+        env::set_var("XDG_STATE_HOME", "/tmp/ciri/summary_test/state");
+
+        assert!(with_summary("build", false, || Ok(())).is_ok());
+        let res = with_summary("test", false, || run_child(cmd!("sh", "-c", "exit 4")));
+        assert!(res.is_err());
+
+        let entries = History::open().unwrap().entries().unwrap();
+        match state {
+            Some(v) => env::set_var("XDG_STATE_HOME", v),
+            None => env::remove_var("XDG_STATE_HOME"),
+        }
+        assert_eq!(
+            entries
+                .iter()
+                .map(|v| (v.command.as_str(), v.exit_code))
+                .collect::<Vec<_>>(),
+            vec![("build", 0), ("test", 4)]
+        );
+        assert_eq!(
+            entries[0].project,
+            std::path::Path::new("/tmp/ciri/summary_test")
+        );
+
+        std::fs::remove_dir_all("/tmp/ciri/summary_test")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use ciri::args::package::{Build, Clean, Run, Test};
+use ciri::entities::limits::ResourceLimits;
+use ciri::entities::task_runner::{parse_just_summary, parse_makefile_targets, TaskRunner};
+use duct::cmd;
+use inquire::Select;
+use miette::{bail, miette, IntoDiagnostic, WrapErr};
+
+use super::process::run_limited;
+use super::tasks::with_build_hooks;
+
+fn program(runner: TaskRunner) -> &'static str {
+    match runner {
+        TaskRunner::Just => "just",
+        TaskRunner::Make => "make",
+    }
+}
+
+/// Targets defined by project's Makefile or justfile
+pub fn targets(runner: TaskRunner) -> miette::Result<Vec<String>> {
+    match runner {
+        TaskRunner::Make => {
+            let file = runner
+                .file(Path::new("."))
+                .ok_or_else(|| miette!("Makefile wasn't found"))?;
+            let data = std::fs::read_to_string(file).into_diagnostic()?;
+            Ok(parse_makefile_targets(&data))
+        }
+        TaskRunner::Just => {
+            let summary = cmd!("just", "--summary")
+                .stderr_null()
+                .read()
+                .into_diagnostic()
+                .wrap_err("Couldn't list just recipes")?;
+            Ok(parse_just_summary(&summary))
+        }
+    }
+}
+
+/// Runs target, or the default one when none is given
+fn run_target(
+    runner: TaskRunner,
+    target: Option<&str>,
+    args: &[String],
+    limits: &ResourceLimits,
+) -> miette::Result<()> {
+    let mut p = target.map(|v| vec![v.to_owned()]).unwrap_or_default();
+    p.extend(args.iter().cloned());
+    run_limited(cmd(program(runner), p), limits)
+        .wrap_err(format!("Target \"{}\" failed", target.unwrap_or("default")))?;
+    Ok(())
+}
+
+/// Runs conventional target of a ciri subcommand
+fn run_conventional(
+    runner: TaskRunner,
+    subcommand: &str,
+    args: &[String],
+    limits: &ResourceLimits,
+) -> miette::Result<()> {
+    let targets = targets(runner)?;
+    match TaskRunner::target_for(subcommand, &targets) {
+        Some(target) => run_target(runner, Some(target), args, limits),
+        // Default goal of a Makefile usually builds the project
+        None if subcommand == "build" && runner == TaskRunner::Make => {
+            run_target(runner, None, args, limits)
+        }
+        None => bail!(
+            "No {} target found, expected one of: {}",
+            subcommand,
+            TaskRunner::conventional_targets(subcommand).join(", ")
+        ),
+    }
+}
+
+pub fn build_from_tasks(args: Build, runner: TaskRunner) -> miette::Result<()> {
+    if args.name.is_some() {
+        bail!("Invalid argument \"name\"");
+    }
+
+    run_conventional(runner, "build", &args.args, &ResourceLimits::default())
+}
+
+pub fn test_from_tasks(args: Test, runner: TaskRunner) -> miette::Result<()> {
+    if args.name.is_some() {
+        bail!("Invalid argument \"name\"");
+    }
+
+    run_conventional(runner, "test", &args.args, &args.limits()?)
+}
+
+pub fn clean_from_tasks(args: Clean, runner: TaskRunner) -> miette::Result<()> {
+    run_conventional(runner, "clean", &args.args, &ResourceLimits::default())
+}
+
+/// Runs given target or asks which one of defined targets to run
+pub fn run_from_tasks(args: Run, runner: TaskRunner) -> miette::Result<()> {
+    let limits = args.limits()?;
+    if args.build {
+        with_build_hooks(args.force, || {
+            run_conventional(runner, "build", &[], &ResourceLimits::default())
+        })?;
+    }
+
+    let target = if let Some(name) = args.name {
+        name.display().to_string()
+    } else {
+        let targets = targets(runner)?;
+        if targets.is_empty() {
+            bail!("No targets were found");
+        }
+        Select::new("What target would you run?", targets)
+            .prompt()
+            .into_diagnostic()?
+    };
+
+    run_target(runner, Some(&target), &args.args, &limits)
+}
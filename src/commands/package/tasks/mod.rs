@@ -0,0 +1,406 @@
+use std::collections::{HashMap, HashSet};
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use ciri::entities::limits::ResourceLimits;
+use ciri::entities::tasks::{resolve, Hook, Task};
+use ciri::Config;
+use duct::cmd;
+use miette::{miette, IntoDiagnostic, Report, WrapErr};
+use tokio::process::Command;
+use tokio::task::JoinSet;
+
+use super::fingerprint::unless_fresh;
+use super::process::{attach, run_child, run_limited, signal_group, ChildExit, KILL_TIMEOUT};
+
+/// Runs task from config in place of manager's command, returns whether it was defined
+///
+/// Extra arguments are appended to command of the task and limits apply to it,
+/// but not to its dependencies
+pub fn run_override(name: &str, args: &[String], limits: &ResourceLimits) -> miette::Result<bool> {
+    let Some(mut tasks) = Config::load()?.tasks else {
+        return Ok(false);
+    };
+    let Some(task) = tasks.get_mut(name) else {
+        return Ok(false);
+    };
+    for arg in args {
+        task.command.push(' ');
+        task.command.push_str(&shell_quote(arg));
+    }
+
+    let mut dependencies = resolve(&tasks, name)?;
+    dependencies.retain(|v| v != name);
+    if !dependencies.is_empty() {
+        execute_all(&tasks, dependencies)?;
+    }
+
+    let task = &tasks[name];
+    info!("Running task {}", name);
+    let mut expression = cmd!("sh", "-c", &task.command);
+    if let Some(cwd) = &task.cwd {
+        expression = expression.dir(cwd);
+    }
+    for (key, value) in task.env.iter().flatten() {
+        expression = expression.env(key, value);
+    }
+    run_limited(expression, limits).wrap_err(format!("Task \"{}\" failed", name))?;
+    Ok(true)
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Runs `pre_` and `post_` hooks of subcommand around it
+///
+/// Post hooks are skipped when subcommand fails
+pub fn with_hooks<T>(subcommand: &str, f: impl FnOnce() -> miette::Result<T>) -> miette::Result<T> {
+    run_hooks(&format!("pre_{}", subcommand))?;
+    let result = f()?;
+    run_hooks(&format!("post_{}", subcommand))?;
+    Ok(result)
+}
+
+/// Runs build between its hooks, `pre_build` ones are skipped when `build_inputs` are set and didn't change
+///
+/// They usually generate code, which doesn't have to be generated again from the same inputs
+pub fn with_build_hooks<T>(
+    force: bool,
+    f: impl FnOnce() -> miette::Result<T>,
+) -> miette::Result<T> {
+    let config = Config::load()?;
+    match &config.build_inputs {
+        Some(inputs)
+            if config
+                .hooks
+                .as_ref()
+                .is_some_and(|v| !v.get("pre_build").is_empty()) =>
+        {
+            let commands = hook_commands(&config, "pre_build")?;
+            if !unless_fresh("pre_build", inputs, &commands, force, || {
+                run_hooks("pre_build")
+            })? {
+                info!("Build inputs didn't change, skipping pre_build hooks");
+            }
+        }
+        _ => run_hooks("pre_build")?,
+    }
+
+    let result = f()?;
+    run_hooks("post_build")?;
+    Ok(result)
+}
+
+/// Commands run by hooks, including ones of tasks they reference
+fn hook_commands(config: &Config, name: &str) -> miette::Result<Vec<String>> {
+    let tasks = config.tasks.clone().unwrap_or_default();
+    let mut commands = vec![];
+    for hook in config.hooks.iter().flat_map(|v| v.get(name)) {
+        match hook {
+            Hook::Command(command) => commands.push(command.clone()),
+            Hook::Task { task } => commands.extend(task_commands(&tasks, task)?),
+        }
+    }
+    Ok(commands)
+}
+
+/// Commands of task and its dependencies with their directories and environment
+pub fn task_commands(tasks: &HashMap<String, Task>, name: &str) -> miette::Result<Vec<String>> {
+    Ok(resolve(tasks, name)?
+        .iter()
+        .map(|v| {
+            let task = &tasks[v];
+            let mut env: Vec<_> = task
+                .env
+                .iter()
+                .flatten()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            env.sort();
+            format!(
+                "{}: {} {} {}",
+                v,
+                task.cwd
+                    .as_ref()
+                    .map(|v| v.display().to_string())
+                    .unwrap_or_default(),
+                env.join(" "),
+                task.command
+            )
+        })
+        .collect())
+}
+
+fn run_hooks(name: &str) -> miette::Result<()> {
+    let config = Config::load()?;
+    let Some(hooks) = &config.hooks else {
+        return Ok(());
+    };
+
+    for hook in hooks.get(name) {
+        match hook {
+            Hook::Command(command) => run_child(cmd!("sh", "-c", command)),
+            Hook::Task { task } => {
+                let tasks = config.tasks.clone().unwrap_or_default();
+                run_task(&tasks, task)
+            }
+        }
+        .wrap_err(format!("Hook \"{}\" failed", name))?;
+    }
+
+    Ok(())
+}
+
+/// Runs task after all tasks it depends on, independent ones are run in parallel
+pub fn run_task(tasks: &HashMap<String, Task>, name: &str) -> miette::Result<()> {
+    execute_all(tasks, resolve(tasks, name)?)
+}
+
+fn execute_all(tasks: &HashMap<String, Task>, order: Vec<String>) -> miette::Result<()> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .into_diagnostic()?
+        .block_on(execute(tasks, order))
+}
+
+/// Process groups of running tasks by their names
+type Groups = Arc<Mutex<HashMap<String, i32>>>;
+
+async fn execute(tasks: &HashMap<String, Task>, mut pending: Vec<String>) -> miette::Result<()> {
+    // Output of tasks running next to each other would be mixed, so it's captured
+    let capture = pending.len() > 1;
+    let mut done: HashSet<String> = HashSet::new();
+    let mut running = JoinSet::new();
+    let groups = Groups::default();
+
+    loop {
+        let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|v| {
+            tasks[v]
+                .dependencies()
+                .iter()
+                .all(|dependency| done.contains(dependency))
+        });
+        pending = rest;
+        for name in ready {
+            let task = tasks[&name].clone();
+            running.spawn(run_one(name, task, capture, groups.clone()));
+        }
+
+        let Some(result) = running.join_next().await else {
+            break;
+        };
+        match result.into_diagnostic().and_then(|v| v) {
+            Ok(name) => done.insert(name),
+            Err(err) => {
+                stop(&groups, &mut running).await;
+                return Err(err);
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Terminates groups of other tasks and kills them when they don't exit in time
+async fn stop(groups: &Groups, running: &mut JoinSet<miette::Result<String>>) {
+    let signal_all = |signal| {
+        for pgid in groups
+            .lock()
+            .map(|v| v.values().copied().collect())
+            .unwrap_or_else(|_| vec![])
+        {
+            signal_group(pgid, signal);
+        }
+    };
+
+    signal_all(libc::SIGTERM);
+    let exited = async { while running.join_next().await.is_some() {} };
+    if tokio::time::timeout(KILL_TIMEOUT, exited).await.is_err() {
+        signal_all(libc::SIGKILL);
+        running.shutdown().await;
+    }
+}
+
+async fn run_one(
+    name: String,
+    task: Task,
+    capture: bool,
+    groups: Groups,
+) -> miette::Result<String> {
+    info!("Running task {}", name);
+    let mut command = std::process::Command::new("sh");
+    // Own group lets us stop everything the task started
+    command.arg("-c").process_group(0);
+    if let Some(cwd) = &task.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(env) = &task.env {
+        command.envs(env);
+    }
+    if capture {
+        // Merge stderr into stdout so output keeps its order
+        command
+            .arg(format!("exec 2>&1\n{}", task.command))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+    } else {
+        command.arg(&task.command);
+    }
+
+    let child = Command::from(command)
+        .kill_on_drop(true)
+        .spawn()
+        .into_diagnostic()?;
+    let pgid = child.id().map(|v| v as i32);
+    if let (Some(pgid), Ok(mut groups)) = (pgid, groups.lock()) {
+        groups.insert(name.clone(), pgid);
+    }
+    // Only task running alone gets the terminal
+    let attached = pgid.map(|v| attach(v, !capture)).transpose()?;
+    let output = child.wait_with_output().await.into_diagnostic();
+    drop(attached);
+    if let Ok(mut groups) = groups.lock() {
+        groups.remove(&name);
+    }
+    let output = output?;
+
+    if capture {
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            return Err(miette!(
+                help = output_str.trim_end().to_owned(),
+                "Task \"{}\" failed with {}",
+                name,
+                output.status
+            ));
+        }
+        print!("{}", output_str);
+    } else if let Some(exit) = ChildExit::from_status(output.status) {
+        // Exit code is kept so ciri exits with it
+        return Err(Report::new(exit).wrap_err(format!("Task \"{}\" failed", name)));
+    }
+
+    Ok(name)
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn task(command: &str, depends_on: &[&str]) -> Task {
+        Task {
+            command: command.to_owned(),
+            depends_on: Some(depends_on.iter().map(|v| v.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn prepare_tasks_test() -> anyhow::Result<()> {
+        let _ = std::fs::remove_dir_all("/tmp/ciri/tasks_test");
+        std::fs::create_dir_all("/tmp/ciri/tasks_test/sub")?;
+        env::set_current_dir("/tmp/ciri/tasks_test")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn run_task_test() -> anyhow::Result<()> {
+        prepare_tasks_test()?;
+
+        let tasks = HashMap::from([
+            ("a".to_owned(), task("touch a", &[])),
+            (
+                "b".to_owned(),
+                Task {
+                    cwd: Some("sub".into()),
+                    env: Some(HashMap::from([("NAME".to_owned(), "b".to_owned())])),
+                    ..task("touch $NAME", &[])
+                },
+            ),
+            (
+                "c".to_owned(),
+                task("test -f a && test -f sub/b", &["a", "b"]),
+            ),
+        ]);
+        assert!(run_task(&tasks, "c").is_ok());
+        assert!(Path::new("sub/b").exists());
+
+        std::fs::remove_dir_all("/tmp/ciri/tasks_test")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn run_override_limits_test() -> anyhow::Result<()> {
+        prepare_tasks_test()?;
+        std::fs::write(
+            ".ciri.toml",
+            "[tasks.prepare]\ncommand = \"sleep 0.5; touch prepared\"\n\n\
+             [tasks.slow]\ncommand = \"sleep 10\"\ndepends_on = [\"prepare\"]\n",
+        )?;
+
+        // Timeout applies to the task itself, not to its dependencies
+        let limits = ResourceLimits {
+            timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+        let err = run_override("slow", &[], &limits).unwrap_err();
+        assert!(err.to_string().starts_with("Task \"slow\" failed"));
+        assert!(Path::new("prepared").exists());
+        assert!(!run_override("missing", &[], &limits).unwrap());
+
+        std::fs::remove_dir_all("/tmp/ciri/tasks_test")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn failing_task_test() -> anyhow::Result<()> {
+        prepare_tasks_test()?;
+
+        let tasks = HashMap::from([
+            ("fail".to_owned(), task("echo boom; exit 3", &[])),
+            ("slow".to_owned(), task("sleep 5; touch slow", &[])),
+            ("all".to_owned(), task("touch all", &["fail", "slow"])),
+        ]);
+        let err = run_task(&tasks, "all").unwrap_err();
+        assert!(err.to_string().starts_with("Task \"fail\" failed"));
+        assert_eq!(err.help().map(|v| v.to_string()), Some("boom".to_owned()));
+        // Other tasks are stopped when one fails
+        assert!(!Path::new("slow").exists());
+        assert!(!Path::new("all").exists());
+
+        std::fs::remove_dir_all("/tmp/ciri/tasks_test")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn failing_task_group_test() -> anyhow::Result<()> {
+        prepare_tasks_test()?;
+
+        let tasks = HashMap::from([
+            ("fail".to_owned(), task("sleep 0.2; exit 3", &[])),
+            (
+                "server".to_owned(),
+                task("(sleep 1; touch leaked) & wait", &[]),
+            ),
+            ("all".to_owned(), task("true", &["fail", "server"])),
+        ]);
+        assert!(run_task(&tasks, "all").is_err());
+        // Processes started by stopped task are stopped with it
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(!Path::new("leaked").exists());
+
+        std::fs::remove_dir_all("/tmp/ciri/tasks_test")?;
+        Ok(())
+    }
+}
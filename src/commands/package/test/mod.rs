@@ -0,0 +1,393 @@
+use std::cmp::Ordering;
+use std::env;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use ciri::args::package::Test;
+use ciri::entities::managers::Manager;
+use ciri::entities::profile::ResolvedProfile;
+use ciri::entities::task_runner::TaskRunner;
+use ciri::entities::test_report::{filter_args, TestCase, TestReport};
+use ciri::validators::detect_language;
+use ciri::{Config, PackageManagers, Util};
+use duct::cmd;
+use inquire::Select;
+use miette::{bail, miette, IntoDiagnostic};
+
+use super::build::{pick_rockspec, sbt_args};
+use super::env::apply_env;
+use super::native::{detect_build_system, test_project};
+use super::task_runner::test_from_tasks;
+use super::tasks::{run_override, with_hooks};
+
+mod report;
+
+pub use report::run_tests;
+
+pub fn test(args: Test) -> miette::Result<()> {
+    apply_env(args.env_profile.as_deref(), args.print_env)?;
+    let browse = args.browse;
+    let targets = args.report.clone();
+    let mut report = TestReport::default();
+
+    let limits = args.limits()?;
+    let result = with_hooks("test", || {
+        if args.name.is_none() && run_override("test", &args.args, &limits)? {
+            return Ok(());
+        }
+
+        let langs = detect_language()?;
+        let lang = match langs.len().cmp(&1) {
+            Ordering::Less => handle_none()?,
+            Ordering::Equal => langs[0].clone(),
+            Ordering::Greater => pick_manager(langs)?,
+        };
+        test_with_retries(&lang, args, &mut report)
+    });
+
+    // Results are shown for failed runs too, their error is more important though
+    if let Err(err) = report::summarize(&report, browse) {
+        warn!("Test results weren't saved: {:?}", err);
+    }
+    if let Err(err) = report::export(&targets, &report, result.is_ok()) {
+        if result.is_ok() {
+            return Err(err);
+        }
+        warn!("{:?}", err);
+    }
+    result
+}
+
+/// Runs tests, failed ones are run again up to `--retries` times
+fn test_with_retries(lang: &str, args: Test, report: &mut TestReport) -> miette::Result<()> {
+    let patterns = Config::load()?.test_reports.unwrap_or_default();
+
+    let started = SystemTime::now();
+    let mut result = if args.failed {
+        let project = env::current_dir().into_diagnostic()?;
+        let Some(last) = TestReport::load(&TestReport::last_path(&project)?)? else {
+            bail!("No test results were saved in this project yet");
+        };
+        let failed: Vec<&TestCase> = last.failures().collect();
+        if failed.is_empty() {
+            info!("No tests failed last time");
+            return Ok(());
+        }
+        test_one(lang, filtered(args.clone(), failed_filter(lang, &failed)?))
+    } else {
+        test_one(lang, args.clone())
+    };
+    *report = report::collect(&patterns, started);
+
+    for attempt in 1..=args.retries {
+        let failed: Vec<&TestCase> = report.failures().collect();
+        if result.is_ok() || failed.is_empty() {
+            break;
+        }
+        let filter = match failed_filter(lang, &failed) {
+            Ok(filter) => filter,
+            Err(err) => {
+                warn!("Failed tests can't be retried: {:?}", err);
+                break;
+            }
+        };
+
+        warn!(
+            "Retrying {} failed test(s), attempt {} of {}",
+            failed.len(),
+            attempt,
+            args.retries
+        );
+        let started = SystemTime::now();
+        result = test_one(lang, filtered(args.clone(), filter));
+        report.retried(report::collect(&patterns, started));
+    }
+
+    result
+}
+
+/// Arguments of manager's test runner that select failed tests
+fn failed_filter(lang: &str, failed: &[&TestCase]) -> miette::Result<Vec<String>> {
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
+    let pkg = bind.to_manager()?;
+
+    let filter = match bind {
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => {
+            let names: Vec<&str> = failed.iter().map(|v| v.name.as_str()).collect();
+            detect_build_system()?.filter_args(&names)
+        }
+        _ => filter_args(pkg.agent, failed),
+    };
+    filter.ok_or_else(|| miette!("Tests run by {} can't be selected by name", pkg.agent))
+}
+
+/// Arguments that run only tests selected by filter
+fn filtered(mut args: Test, filter: Vec<String>) -> Test {
+    args.name = None;
+    // Filter can end with `--`, so user's arguments go after it
+    args.args.splice(0..0, filter);
+    args
+}
+
+fn test_one(lang: &str, args: Test) -> miette::Result<()> {
+    let bind = PackageManagers::from_str(lang)?.with_config(&Config::load()?);
+    let pkg = bind.to_manager()?;
+
+    match bind {
+        PackageManagers::Bun
+        | PackageManagers::Npm
+        | PackageManagers::Yarn
+        | PackageManagers::Pnpm
+        | PackageManagers::Cargo => test_from_manager(args, pkg),
+        PackageManagers::Lua => test_from_rockspec(args, pkg),
+        PackageManagers::Scala => test_from_scala(args, pkg),
+        PackageManagers::Swift => test_from_swift(args, pkg),
+        PackageManagers::Gcc
+        | PackageManagers::Clang
+        | PackageManagers::Gpp
+        | PackageManagers::Clangpp => test_native(args, pkg),
+        PackageManagers::Make | PackageManagers::Just => {
+            test_from_tasks(args, TaskRunner::from_manager(&bind).unwrap())
+        }
+        _ => bail!("Package manager was not yet implemented"),
+    }
+}
+
+pub fn test_from_manager(args: Test, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(test) = pkg.test else {
+        bail!("Test script not found");
+    };
+
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    let (program, mut p) = test.to_args()?;
+    p.extend(profile.args.iter().map(String::as_str));
+    let name = args.name.map(|v| v.display().to_string());
+    if let Some(name) = &name {
+        p.push(name);
+    }
+    pkg.push_args("test", &mut p, &args.args);
+    run_tests(profile.apply(cmd(program, p)), &limits)?;
+
+    Ok(())
+}
+
+pub fn test_from_rockspec(args: Test, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(test) = pkg.test else {
+        bail!("Test script not found");
+    };
+
+    let rockspec = pick_rockspec()?.display().to_string();
+    let (program, mut p) = test.to_args()?;
+    p.push(&rockspec);
+
+    // Everything after rockspec is passed to the test backend (busted by default)
+    let name = args.name.map(|v| v.display().to_string());
+    if name.is_some() || !args.args.is_empty() {
+        p.push("--");
+    }
+    if let Some(name) = &name {
+        p.push(name);
+    }
+    p.extend(args.args.iter().map(String::as_str));
+    run_tests(cmd(program, p), &limits)?;
+
+    Ok(())
+}
+
+pub fn test_from_scala(args: Test, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(test) = pkg.test else {
+        bail!("Test script not found");
+    };
+
+    let name = args.name.map(|v| v.display().to_string());
+    // Arguments before `--` select suites, the rest is passed to test framework
+    let (selection, framework) = match args.args.iter().position(|v| v == "--") {
+        Some(i) => (args.args[..i].to_vec(), args.args[i + 1..].to_vec()),
+        None => (vec![], args.args),
+    };
+    if pkg.agent == "sbt" {
+        // Only `testOnly` accepts test filters and framework arguments
+        let (program, p) = if name.is_none() && selection.is_empty() && framework.is_empty() {
+            sbt_args(test, &[])?
+        } else {
+            let mut task_args = selection;
+            if task_args.is_empty() {
+                task_args.push(name.unwrap_or("*".to_owned()));
+            }
+            if !framework.is_empty() {
+                task_args.push("--".to_owned());
+                task_args.extend(framework);
+            }
+            sbt_args("sbt -batch testOnly", &task_args)?
+        };
+        run_tests(cmd(program, p), &limits)?;
+    } else {
+        // scala-cli takes sources to test in place of the current directory
+        let (program, mut p) = test.to_args()?;
+        if let Some(name) = &name {
+            p.pop();
+            p.push(name);
+        }
+        p.extend(selection.iter().map(String::as_str));
+        pkg.push_args("test", &mut p, &framework);
+        run_tests(cmd(program, p), &limits)?;
+    }
+
+    Ok(())
+}
+
+pub fn test_from_swift(args: Test, pkg: Manager) -> miette::Result<()> {
+    let limits = args.limits()?;
+    let Some(test) = pkg.test else {
+        bail!("Test script not found");
+    };
+
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    let (program, mut p) = test.to_args()?;
+    p.extend(profile.args.iter().map(String::as_str));
+    let name = args.name.map(|v| v.display().to_string());
+    if let Some(name) = &name {
+        p.extend(["--filter", name]);
+    }
+    pkg.push_args("test", &mut p, &args.args);
+    run_tests(profile.apply(cmd(program, p)), &limits)?;
+
+    Ok(())
+}
+
+/// Builds project and runs ctest, name is used as test filter
+pub fn test_native(args: Test, pkg: Manager) -> miette::Result<()> {
+    let profile =
+        ResolvedProfile::load(pkg.agent, args.release, args.debug, args.profile.as_deref())?;
+    let limits = args.limits()?;
+    let name = args.name.map(|v| v.display().to_string());
+    test_project(&pkg, &profile, &limits, name.as_deref(), &args.args)
+}
+
+fn pick_manager(langs: Vec<String>) -> miette::Result<String> {
+    Select::new("What package manager would you use?", langs)
+        .prompt()
+        .into_diagnostic()
+}
+
+fn handle_none<T>() -> miette::Result<T> {
+    bail!("No valid package manager was detected")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use ciri::entities::export::parse_report_target;
+    use ciri::entities::test_report::TestStatus;
+
+    const LIB: &str = r#"
+#[test]
+fn stable() {}
+
+// Fails and passes in turns
+#[test]
+fn flaky() {
+    let marker = std::path::Path::new("marker");
+    if marker.exists() {
+        std::fs::remove_file(marker).unwrap();
+    } else {
+        std::fs::write(marker, "").unwrap();
+        panic!("first try");
+    }
+}
+
+#[test]
+fn broken() {
+    panic!("always");
+}
+"#;
+
+    fn last_report() -> Vec<(String, TestStatus, bool)> {
+        let path = TestReport::last_path(&env::current_dir().unwrap()).unwrap();
+        TestReport::load(&path)
+            .unwrap()
+            .unwrap()
+            .cases
+            .into_iter()
+            .map(|v| (v.name, v.status, v.flaky))
+            .collect()
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_retries_test() -> anyhow::Result<()> {
+        let dir = "/tmp/ciri/test_retries_test";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(format!("{}/src", dir))?;
+        fs::write(
+            format!("{}/Cargo.toml", dir),
+            "[package]\nname = \"retries\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )?;
+        fs::write(format!("{}/src/lib.rs", dir), LIB)?;
+        env::set_current_dir(dir)?;
+        let state = env::var_os("XDG_STATE_HOME");
+        env::set_var("XDG_STATE_HOME", format!("{}/state", dir));
+
+        let run = |failed: bool, retries: u32| {
+            test(Test {
+                failed,
+                retries,
+                report: vec![parse_report_target("json=report.json").unwrap()],
+                ..Test::new(None, false)
+            })
+        };
+        let failed = run(true, 0);
+        let first = run(false, 0);
+        let second = run(true, 1);
+        let after_failed = last_report();
+        let third = run(false, 1);
+        let after_retry = last_report();
+        let exported: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string("report.json")?)?;
+
+        match state {
+            Some(v) => env::set_var("XDG_STATE_HOME", v),
+            None => env::remove_var("XDG_STATE_HOME"),
+        }
+        fs::remove_dir_all(dir)?;
+
+        // Nothing was saved yet
+        assert!(failed.is_err());
+        assert!(first.is_err());
+        // Only failed tests were run, flaky one passed at once
+        assert!(second.is_err());
+        assert_eq!(
+            after_failed,
+            vec![
+                ("broken".to_owned(), TestStatus::Failed, false),
+                ("flaky".to_owned(), TestStatus::Passed, false),
+            ]
+        );
+        assert!(third.is_err());
+        assert_eq!(
+            after_retry,
+            vec![
+                ("broken".to_owned(), TestStatus::Failed, false),
+                ("flaky".to_owned(), TestStatus::Passed, true),
+                ("stable".to_owned(), TestStatus::Passed, false),
+            ]
+        );
+        assert_eq!(exported["success"], false);
+        assert_eq!(
+            exported["totals"],
+            serde_json::json!({"tests": 3, "passed": 2, "failed": 1, "skipped": 0, "flaky": 1})
+        );
+        Ok(())
+    }
+}
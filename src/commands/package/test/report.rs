@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::time::SystemTime;
+
+use ciri::entities::export::{test_report, ReportTarget};
+use ciri::entities::limits::ResourceLimits;
+use ciri::entities::test_report::{TestReport, TestStatus};
+use ciri::parsers::junit::parse_junit;
+use ciri::parsers::test_output::TestOutputParser;
+use duct::Expression;
+use miette::{bail, miette, IntoDiagnostic, WrapErr};
+use ratatui::layout::Constraint;
+use ratatui::style::Stylize;
+use ratatui::widgets::Cell;
+
+use crate::commands::package::process::run_lines;
+use crate::components::table::{run_app, Table};
+use crate::components::{finalize_app, prepare_app};
+
+thread_local! {
+    /// Results of test commands run by current invocation
+    static REPORT: RefCell<TestReport> = RefCell::new(TestReport::default());
+}
+
+/// Runs tests like [`run_limited`](crate::commands::package::process::run_limited), results found in their
+/// output are added to report of this run
+///
+/// Output is printed to stderr as it comes, so stdout stays free for `--json`
+pub fn run_tests(expression: Expression, limits: &ResourceLimits) -> miette::Result<()> {
+    let mut parser = TestOutputParser::new();
+    let result = run_lines(expression, limits, |line| {
+        eprintln!("{}", line);
+        parser.push(line);
+    });
+
+    REPORT.with(|v| v.borrow_mut().extend(parser.finish()));
+    result
+}
+
+/// Takes report of this run, JUnit files matched by `patterns` that were written since
+/// `started` replace results parsed from output
+pub fn take_report(patterns: &[String], started: SystemTime) -> miette::Result<TestReport> {
+    let parsed = REPORT.with(|v| v.take());
+
+    let mut junit = TestReport::default();
+    for pattern in patterns {
+        let paths = glob::glob(pattern)
+            .map_err(|e| miette!("Invalid test report \"{}\": {}", pattern, e))?;
+        for path in paths.filter_map(Result::ok) {
+            let fresh = fs::metadata(&path)
+                .and_then(|v| v.modified())
+                .is_ok_and(|v| v >= started);
+            if !fresh {
+                continue;
+            }
+
+            let xml = fs::read_to_string(&path).into_diagnostic()?;
+            junit.extend(
+                parse_junit(&xml)
+                    .wrap_err(format!("Report \"{}\" can't be read", path.display()))?,
+            );
+        }
+    }
+
+    Ok(if junit.is_empty() { parsed } else { junit })
+}
+
+/// Report of this run like [`take_report`], failing to read JUnit files is only a warning
+pub fn collect(patterns: &[String], started: SystemTime) -> TestReport {
+    take_report(patterns, started).unwrap_or_else(|err| {
+        warn!("Test results weren't read: {:?}", err);
+        TestReport::default()
+    })
+}
+
+/// Prints counts of results and saves them for `--failed`, then lets user browse failures
+pub fn summarize(report: &TestReport, browse: bool) -> miette::Result<()> {
+    if !report.is_empty() {
+        info!("Tests: {}", report);
+        let project = env::current_dir().into_diagnostic()?;
+        report.save(&TestReport::last_path(&project)?)?;
+    }
+    if browse {
+        failures_table(report)?;
+    }
+    Ok(())
+}
+
+/// Writes results of this run to every `--report` target
+///
+/// Report of successful run without any results would hide that output of runner isn't parsed
+pub fn export(targets: &[ReportTarget], report: &TestReport, success: bool) -> miette::Result<()> {
+    if !targets.is_empty() && success && report.cases.is_empty() {
+        bail!(
+            help = "Let test runner write JUnit XML and list it in test_reports of .ciri.toml",
+            "No test results were found, so there is nothing to report"
+        );
+    }
+    for target in targets {
+        target.write(&test_report(target.format, report, success))?;
+    }
+    Ok(())
+}
+
+/// Table of failed and flaky tests, Enter shows whole output of selected one
+fn failures_table(report: &TestReport) -> miette::Result<()> {
+    let rows = rows(report);
+    if rows.is_empty() {
+        info!("No tests failed or were flaky");
+        return Ok(());
+    }
+
+    let headers = vec!["Suite", "Test", "Duration", "Output"];
+    let widths = vec![
+        Constraint::Percentage(15),
+        Constraint::Percentage(25),
+        Constraint::Percentage(10),
+        Constraint::Percentage(50),
+    ];
+    let app = Table::new(
+        "Failed Tests",
+        None,
+        headers,
+        rows,
+        widths,
+        Some(|_, multiline_cell, j| match j {
+            1 => Cell::from(multiline_cell).red(),
+            _ => Cell::from(multiline_cell),
+        }),
+    );
+
+    let mut terminal = prepare_app()?;
+    run_app(app, &mut terminal)?;
+    finalize_app(terminal)?;
+
+    Ok(())
+}
+
+fn rows(report: &TestReport) -> Vec<Vec<String>> {
+    report
+        .cases
+        .iter()
+        .filter(|v| v.status == TestStatus::Failed || v.flaky)
+        .map(|case| {
+            vec![
+                case.suite.clone().unwrap_or_default(),
+                if case.flaky {
+                    format!("{} (flaky)", case.name)
+                } else {
+                    case.name.clone()
+                },
+                case.duration
+                    .map(|v| format!("{:.2}s", v))
+                    .unwrap_or_default(),
+                case.output.clone().unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use duct::cmd;
+
+    #[test]
+    fn run_tests_test() -> miette::Result<()> {
+        let started = SystemTime::now();
+        let script = "echo 'test a ... ok'; echo 'test b ... FAILED'; exit 101";
+        assert!(run_tests(cmd!("sh", "-c", script), &ResourceLimits::default()).is_err());
+        run_tests(
+            cmd!("sh", "-c", "echo 'ok 1 - c # SKIP'"),
+            &ResourceLimits::default(),
+        )?;
+
+        let report = take_report(&[], started)?;
+        assert_eq!(report.to_string(), "1 passed, 1 failed, 1 skipped");
+        assert_eq!(rows(&report), vec![vec!["", "b", "", ""]]);
+        assert!(take_report(&[], started)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn take_report_test() -> miette::Result<()> {
+        let dir = "/tmp/ciri/take_report_test";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).into_diagnostic()?;
+        let pattern = vec![format!("{}/*.xml", dir)];
+        fs::write(
+            format!("{}/old.xml", dir),
+            "<testsuite><testcase name=\"old\"/></testsuite>",
+        )
+        .into_diagnostic()?;
+
+        // Timestamps of files are coarser than system time
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let started = SystemTime::now();
+        run_tests(cmd!("echo", "test a ... ok"), &ResourceLimits::default())?;
+        // Files left by earlier runs are ignored
+        assert_eq!(take_report(&pattern, started)?.cases[0].name, "a");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        fs::write(
+            format!("{}/new.xml", dir),
+            "<testsuite name=\"s\"><testcase name=\"new\"><skipped/></testcase></testsuite>",
+        )
+        .into_diagnostic()?;
+        run_tests(cmd!("echo", "test a ... ok"), &ResourceLimits::default())?;
+        let report = take_report(&pattern, started)?;
+        assert_eq!(report.cases.len(), 1);
+        assert_eq!(report.cases[0].full_name(), "s::new");
+        assert_eq!(report.cases[0].status, TestStatus::Skipped);
+
+        fs::write(format!("{}/new.xml", dir), "<testsuite>").into_diagnostic()?;
+        assert!(take_report(&pattern, started).is_err());
+        fs::remove_dir_all(dir).into_diagnostic()?;
+        Ok(())
+    }
+
+    #[test]
+    fn export_test() -> miette::Result<()> {
+        let path = "/tmp/ciri/export_test.json";
+        let targets = vec![
+            ciri::entities::export::parse_report_target(&format!("json={}", path))
+                .map_err(|e| miette!(e))?,
+        ];
+
+        // Nothing was parsed from output of runner
+        assert!(export(&targets, &TestReport::default(), true).is_err());
+        assert!(export(&[], &TestReport::default(), true).is_ok());
+
+        export(&targets, &TestReport::default(), false)?;
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).into_diagnostic()?).into_diagnostic()?;
+        assert_eq!(json["success"], false);
+        fs::remove_file(path).into_diagnostic()?;
+        Ok(())
+    }
+}
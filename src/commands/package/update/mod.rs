@@ -0,0 +1,80 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use ciri::args::package::Update;
+use ciri::validators::detect_language;
+use ciri::{Config, PackageManagers, Util};
+use duct::cmd;
+use inquire::Select;
+use miette::{bail, IntoDiagnostic};
+
+pub fn update(args: Update) -> miette::Result<()> {
+    let langs = detect_language()?;
+    match langs.len().cmp(&1) {
+        Ordering::Less => bail!("No valid package manager was detected"),
+        Ordering::Equal => update_one(langs.first().unwrap(), args),
+        Ordering::Greater => {
+            let manager = Select::new("What package manager would you use?", langs)
+                .prompt()
+                .into_diagnostic()?;
+            update_one(&manager, args)
+        }
+    }
+}
+
+fn update_one(lang: &str, args: Update) -> miette::Result<()> {
+    let pkg = PackageManagers::from_str(lang)?
+        .with_config(&Config::load()?)
+        .to_manager()?;
+    if pkg.upgrade.is_empty() {
+        bail!("Dependencies of {} projects can't be updated", pkg.agent);
+    }
+
+    let (program, mut p) = pkg.upgrade.to_args()?;
+    if let Some(name) = &args.name {
+        p.push(name);
+    }
+    pkg.push_args("update", &mut p, &args.args);
+    cmd(program, p).run().into_diagnostic()?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[serial_test::serial]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    #[serial_test::serial]
+    fn update_test() -> anyhow::Result<()> {
+        let _ = std::fs::remove_dir_all("/tmp/ciri/update_test");
+        std::fs::create_dir_all("/tmp/ciri/update_test")?;
+        cmd!(
+            "cp",
+            "-r",
+            format!("{}/example_projects/rust/.", env!("CARGO_MANIFEST_DIR")),
+            "/tmp/ciri/update_test"
+        )
+        .run()?;
+        env::set_current_dir("/tmp/ciri/update_test")?;
+
+        let with_args = |args: &[&str]| {
+            update(Update {
+                args: args.iter().map(|v| v.to_string()).collect(),
+                ..Update::new(None)
+            })
+        };
+        // Arguments reach cargo, which rejects unknown ones
+        assert!(with_args(&["--dry-run"]).is_ok());
+        assert!(with_args(&["--no-such-flag"]).is_err());
+
+        std::fs::write("Makefile", "all:\n\ttrue\n")?;
+        std::fs::remove_file("Cargo.toml")?;
+        assert!(update(Update::new(None)).is_err());
+
+        std::fs::remove_dir_all("/tmp/ciri/update_test")?;
+        Ok(())
+    }
+}
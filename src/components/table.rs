@@ -19,10 +19,12 @@ pub struct Table<'a> {
     state: TableState,
     /// Items to be parsed as rows
     items: Vec<Vec<String>>,
-    /// List of maximum available height for rows
+    /// List of maximum available height for items
     max_heights: Vec<u16>,
-    /// List of current height for rows
+    /// List of current height for items, kept while filter changes
     current_heights: Vec<u16>,
+    /// Indexes of items shown as rows
+    visible: Vec<usize>,
     /// Table Headers
     headers: Vec<&'a str>,
     /// Table Rows
@@ -54,6 +56,7 @@ impl<'a> Table<'a> {
             state: TableState::default(),
             max_heights: vec![1; items.len()],
             current_heights: vec![1; items.len()],
+            visible: Vec::new(),
             items,
             headers,
             rows: Vec::new(),
@@ -71,13 +74,10 @@ impl<'a> Table<'a> {
     pub fn next(&mut self, count: usize) {
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.rows.len() - count {
-                    if i.checked_sub(count).is_some() {
-                        self.current_heights[i - count] = 1;
-                    }
+                self.collapse(i);
+                if i + count >= self.rows.len() {
                     0
                 } else {
-                    self.current_heights[i] = 1;
                     i + count
                 }
             }
@@ -89,9 +89,9 @@ impl<'a> Table<'a> {
     pub fn previous(&mut self, count: usize) {
         let i = match self.state.selected() {
             Some(i) => {
-                self.current_heights[i] = 1;
+                self.collapse(i);
                 if i < count {
-                    self.rows.len() - count
+                    self.rows.len().saturating_sub(count)
                 } else {
                     i - count
                 }
@@ -101,8 +101,9 @@ impl<'a> Table<'a> {
         self.state.select(Some(i));
     }
 
+    /// Shows whole content of selected row, or only its first line when it's expanded
     pub fn expand(&mut self) {
-        if let Some(i) = self.state.selected() {
+        if let Some(&i) = self.state.selected().and_then(|v| self.visible.get(v)) {
             if self.current_heights[i] == 1 {
                 self.current_heights[i] = self.max_heights[i];
             } else {
@@ -110,6 +111,12 @@ impl<'a> Table<'a> {
             }
         }
     }
+
+    fn collapse(&mut self, row: usize) {
+        if let Some(&i) = self.visible.get(row) {
+            self.current_heights[i] = 1;
+        }
+    }
 }
 
 pub fn run_app<B: Backend>(mut app: Table, terminal: &mut Terminal<B>) -> miette::Result<()> {
@@ -219,33 +226,43 @@ fn ui(f: &mut Frame, app: &mut Table) {
     .height(1)
     .bottom_margin(1);
 
-    // Reset to full state
-    app.max_heights = vec![1; app.items.len()];
-    app.current_heights = vec![1; app.items.len()];
-
-    let rows: Vec<Row<'_>> = app
+    app.visible = app
         .items
         .iter()
         .enumerate()
         .filter(|(_, v)| v.iter().any(|str| str.contains(&app.input.message)))
-        .map(|(i, item)| {
+        .map(|(i, _)| i)
+        .collect();
+
+    let rows: Vec<Row<'_>> = app
+        .visible
+        .iter()
+        .map(|&i| {
+            let item = &app.items[i];
             let mut new_height = vec![];
 
             let cells: Vec<Cell> = item
                 .iter()
                 .enumerate()
                 .map(|(j, cell)| {
-                    let height = app.widths[j].apply(f.size().width);
-                    let binding = cell.chars().collect::<Vec<_>>();
-                    let vec = binding.chunks(height as usize);
-
-                    let multiline_cell = vec
-                        .clone()
-                        .map(|v| v.iter().collect::<String>())
-                        .collect::<Vec<_>>()
-                        .join("\n");
+                    let width = app.widths[j].apply(f.size().width).max(1);
+                    // Lines of content are wrapped to width of column
+                    let lines = cell
+                        .split('\n')
+                        .flat_map(|line| {
+                            let binding = line.chars().collect::<Vec<_>>();
+                            if binding.is_empty() {
+                                return vec![String::new()];
+                            }
+                            binding
+                                .chunks(width as usize)
+                                .map(|v| v.iter().collect::<String>())
+                                .collect()
+                        })
+                        .collect::<Vec<_>>();
 
-                    new_height.push(vec.len());
+                    let multiline_cell = lines.join("\n");
+                    new_height.push(lines.len());
 
                     if let Some(callback) = app.callback {
                         callback(cell, multiline_cell, j)
@@ -261,8 +278,6 @@ fn ui(f: &mut Frame, app: &mut Table) {
         .collect();
 
     app.rows = rows;
-    app.max_heights = vec![1; app.rows.len()];
-    app.current_heights = vec![1; app.rows.len()];
 
     let t = RatTable::new(app.rows.clone(), app.widths.clone())
         .header(header)
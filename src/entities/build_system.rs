@@ -0,0 +1,832 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{IntoDiagnostic, WrapErr};
+use serde::Deserialize;
+
+use super::test_report::escape_regex;
+
+/// Build systems used by C and C++ projects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildSystem {
+    CMake,
+    Meson,
+    Autotools,
+    Make,
+}
+
+/// Single command to execute together with its environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+impl Step {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_owned(),
+            args: args.iter().map(|v| v.to_string()).collect(),
+            env: vec![],
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+impl BuildSystem {
+    /// Files by which build systems are recognized, in order of priority
+    const FILES: [(&'static str, Self); 9] = [
+        ("CMakeLists.txt", Self::CMake),
+        ("meson.build", Self::Meson),
+        ("configure.ac", Self::Autotools),
+        ("configure.in", Self::Autotools),
+        ("autogen.sh", Self::Autotools),
+        ("configure", Self::Autotools),
+        ("GNUmakefile", Self::Make),
+        ("Makefile", Self::Make),
+        ("makefile", Self::Make),
+    ];
+
+    pub fn detect(dir: &Path) -> Option<Self> {
+        Self::FILES
+            .iter()
+            .find(|(file, _)| dir.join(file).exists())
+            .map(|(_, system)| *system)
+    }
+
+    /// Directory where build outputs are placed
+    pub fn build_dir(&self) -> &'static str {
+        match self {
+            Self::CMake => "build",
+            Self::Meson => "builddir",
+            Self::Autotools | Self::Make => ".",
+        }
+    }
+
+    /// Whether build directory was already set up and configure step can be skipped
+    pub fn is_configured(&self, build_dir: &Path) -> bool {
+        match self {
+            Self::CMake | Self::Meson => build_dir.exists(),
+            Self::Autotools => build_dir.join("Makefile").exists(),
+            Self::Make => true,
+        }
+    }
+
+    /// Step generating `configure` script when project doesn't ship one
+    pub fn bootstrap_step(&self, dir: &Path) -> Option<Step> {
+        if *self != Self::Autotools || dir.join("configure").exists() {
+            return None;
+        }
+
+        if dir.join("autogen.sh").exists() {
+            Some(Step::new("sh", &["autogen.sh"]))
+        } else {
+            Some(Step::new("autoreconf", &["--install"]))
+        }
+    }
+
+    /// Directories containing only build outputs
+    pub fn output_dirs(&self) -> &'static [&'static str] {
+        match self {
+            Self::CMake => &["build"],
+            Self::Meson => &["builddir"],
+            Self::Autotools => &["autom4te.cache"],
+            Self::Make => &[],
+        }
+    }
+
+    /// Command removing outputs that are mixed with sources
+    pub fn clean_step(&self) -> Option<Step> {
+        match self {
+            // Also removes files generated by configure
+            Self::Autotools => Some(Step::new("make", &["distclean"])),
+            Self::Make => Some(Step::new("make", &["clean"])),
+            _ => None,
+        }
+    }
+
+    /// Steps needed to configure and build project
+    pub fn build_steps(&self, options: &BuildOptions) -> Vec<Step> {
+        let (env, cmake_var) = compiler_variables(&options.compiler);
+        let build_dir = options.build_dir.as_str();
+
+        match self {
+            Self::CMake => {
+                let mut configure = if let Some(preset) = &options.preset {
+                    Step::new("cmake", &["--preset", preset])
+                } else {
+                    let mut step = Step::new("cmake", &["-B", build_dir]);
+                    // Generator and toolchain of already configured directory can't be changed
+                    if !options.configured {
+                        if let Some(generator) = &options.generator {
+                            step = step.arg("-G").arg(generator);
+                        }
+                        if let Some(toolchain) = &options.toolchain_file {
+                            step = step.arg(format!("-DCMAKE_TOOLCHAIN_FILE={}", toolchain));
+                        }
+                    }
+                    step
+                };
+                configure = configure.arg("-DCMAKE_EXPORT_COMPILE_COMMANDS=ON");
+                // Presets choose their own compiler, changing it would wipe the cache
+                if options.preset.is_none() {
+                    configure = configure.arg(format!("-D{}={}", cmake_var, options.compiler));
+                }
+                if let Some(build_type) = options.build_type {
+                    configure = configure.arg(format!("-DCMAKE_BUILD_TYPE={}", build_type));
+                }
+                if !options.flags.is_empty() {
+                    let flags = if env == "CXX" {
+                        "CMAKE_CXX_FLAGS"
+                    } else {
+                        "CMAKE_C_FLAGS"
+                    };
+                    configure = configure.arg(format!("-D{}={}", flags, options.flags.join(" ")));
+                }
+
+                let mut build = if let Some(preset) = &options.build_preset {
+                    Step::new("cmake", &["--build", "--preset", preset])
+                } else {
+                    Step::new("cmake", &["--build", build_dir])
+                };
+                // Only used by multi-config generators
+                if let Some(build_type) = options.build_type {
+                    build = build.arg("--config").arg(build_type.to_string());
+                }
+
+                vec![configure, build]
+            }
+            Self::Meson => {
+                let flags = (!options.flags.is_empty()).then(|| {
+                    let flags = if env == "CXX" { "cpp_args" } else { "c_args" };
+                    format!("-D{}={}", flags, meson_array(&options.flags))
+                });
+
+                let mut steps = vec![];
+                if !options.configured {
+                    let mut setup =
+                        Step::new("meson", &["setup", build_dir]).env(env, &options.compiler);
+                    if let Some(build_type) = options.build_type {
+                        setup = setup.arg(format!("--buildtype={}", build_type.meson()));
+                    }
+                    setup.args.extend(flags);
+                    steps.push(setup);
+                } else if options.build_type.is_some() || flags.is_some() {
+                    let mut configure = Step::new("meson", &["configure", build_dir]);
+                    if let Some(build_type) = options.build_type {
+                        configure = configure.arg(format!("-Dbuildtype={}", build_type.meson()));
+                    }
+                    configure.args.extend(flags);
+                    steps.push(configure);
+                }
+                steps.push(Step::new("meson", &["compile", "-C", build_dir]));
+                steps
+            }
+            Self::Autotools => {
+                let flags_var = if env == "CXX" { "CXXFLAGS" } else { "CFLAGS" };
+                let flags = options
+                    .build_type
+                    .map(|v| v.optimization_flags().to_owned())
+                    .into_iter()
+                    .chain(options.flags.iter().cloned())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let mut steps = vec![];
+                let mut make = Step::new("make", &[]);
+                if !options.configured {
+                    steps.extend(options.bootstrap.clone());
+                    let mut configure =
+                        Step::new("./configure", &[]).arg(format!("{}={}", env, options.compiler));
+                    if !flags.is_empty() {
+                        configure = configure.arg(format!("{}={}", flags_var, flags));
+                    }
+                    steps.push(configure);
+                } else if !options.flags.is_empty() {
+                    // Configured flags are replaced only for this build
+                    make = make.arg(format!("{}={}", flags_var, flags));
+                }
+                steps.push(make);
+                steps
+            }
+            Self::Make => {
+                let mut make = Step::new("make", &[]).arg(format!("{}={}", env, options.compiler));
+                if !options.flags.is_empty() {
+                    let flags = if env == "CXX" { "CXXFLAGS" } else { "CFLAGS" };
+                    make = make.arg(format!("{}={}", flags, options.flags.join(" ")));
+                }
+                vec![make]
+            }
+        }
+    }
+
+    /// Steps running tests of already built project
+    ///
+    /// `filter` selects tests by name
+    pub fn test_steps(&self, options: &BuildOptions, filter: Option<&str>) -> Vec<Step> {
+        match self {
+            Self::CMake => {
+                let mut step = if let Some(preset) = &options.test_preset {
+                    Step::new("ctest", &["--preset", preset])
+                } else {
+                    Step::new("ctest", &["--test-dir", &options.build_dir])
+                };
+                step = step.arg("--output-on-failure");
+                if let Some(build_type) = options.build_type {
+                    step = step.arg("-C").arg(build_type.to_string());
+                }
+                if let Some(filter) = filter {
+                    step = step.arg("-R").arg(filter);
+                }
+                vec![step]
+            }
+            Self::Meson => {
+                let mut step = Step::new("meson", &["test", "-C", &options.build_dir])
+                    .arg("--print-errorlogs");
+                if let Some(filter) = filter {
+                    step = step.arg(filter);
+                }
+                vec![step]
+            }
+            Self::Autotools => {
+                let mut step = Step::new("make", &["check"]);
+                if let Some(filter) = filter {
+                    step = step.arg(format!("TESTS={}", filter));
+                }
+                vec![step]
+            }
+            Self::Make => vec![],
+        }
+    }
+
+    /// Arguments of last test step that select only tests with `names`
+    pub fn filter_args(&self, names: &[&str]) -> Option<Vec<String>> {
+        match self {
+            Self::CMake => {
+                let names: Vec<String> = names.iter().map(|v| escape_regex(v)).collect();
+                Some(vec!["-R".to_owned(), format!("^({})$", names.join("|"))])
+            }
+            Self::Meson => Some(names.iter().map(|v| v.to_string()).collect()),
+            Self::Autotools => Some(vec![format!("TESTS={}", names.join(" "))]),
+            Self::Make => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildType {
+    Debug,
+    Release,
+    RelWithDebInfo,
+    MinSizeRel,
+}
+
+impl BuildType {
+    /// Build type by its CMake name, case is ignored
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "debug" => Some(Self::Debug),
+            "release" => Some(Self::Release),
+            "relwithdebinfo" => Some(Self::RelWithDebInfo),
+            "minsizerel" => Some(Self::MinSizeRel),
+            _ => None,
+        }
+    }
+
+    /// Compiler flags used when build system has no notion of build type
+    pub fn optimization_flags(&self) -> &'static str {
+        match self {
+            Self::Debug => "-g -O0",
+            Self::Release => "-O2",
+            Self::RelWithDebInfo => "-O2 -g",
+            Self::MinSizeRel => "-Os",
+        }
+    }
+
+    pub fn meson(&self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Release => "release",
+            Self::RelWithDebInfo => "debugoptimized",
+            Self::MinSizeRel => "minsize",
+        }
+    }
+}
+
+impl Display for BuildType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Debug => write!(f, "Debug"),
+            Self::Release => write!(f, "Release"),
+            Self::RelWithDebInfo => write!(f, "RelWithDebInfo"),
+            Self::MinSizeRel => write!(f, "MinSizeRel"),
+        }
+    }
+}
+
+/// Everything that changes how a project is configured and built
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    pub compiler: String,
+    pub build_dir: String,
+    /// Build directory was already set up
+    pub configured: bool,
+    pub build_type: Option<BuildType>,
+    /// CMake generator used for fresh build directories
+    pub generator: Option<String>,
+    /// CMake configure preset
+    pub preset: Option<String>,
+    /// CMake build preset matching the configure preset
+    pub build_preset: Option<String>,
+    /// CMake test preset matching the configure preset
+    pub test_preset: Option<String>,
+    /// CMake toolchain file, used by vcpkg to provide dependencies
+    pub toolchain_file: Option<String>,
+    /// Autotools step generating configure script
+    pub bootstrap: Option<Step>,
+    /// Compiler flags of selected profile
+    pub flags: Vec<String>,
+}
+
+/// Contents of `CMakePresets.json` and `CMakeUserPresets.json`
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CMakePresets {
+    #[serde(default)]
+    pub configure_presets: Vec<ConfigurePreset>,
+    #[serde(default)]
+    pub build_presets: Vec<StepPreset>,
+    #[serde(default)]
+    pub test_presets: Vec<StepPreset>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurePreset {
+    pub name: String,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub inherits: Inherits,
+    pub binary_dir: Option<String>,
+}
+
+/// Build or test preset
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StepPreset {
+    pub name: String,
+    #[serde(default)]
+    pub hidden: bool,
+    pub configure_preset: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(untagged)]
+pub enum Inherits {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl CMakePresets {
+    /// Reads presets from project directory. User presets are merged into project ones
+    pub fn read(dir: &Path) -> miette::Result<Option<Self>> {
+        let mut presets: Option<Self> = None;
+        for file in ["CMakePresets.json", "CMakeUserPresets.json"] {
+            let path = dir.join(file);
+            if !path.exists() {
+                continue;
+            }
+
+            let data = fs::read_to_string(&path).into_diagnostic()?;
+            let read: Self = serde_json::from_str(&data)
+                .into_diagnostic()
+                .wrap_err(format!("Failed to parse {}", file))?;
+            let presets = presets.get_or_insert_with(Self::default);
+            presets.configure_presets.extend(read.configure_presets);
+            presets.build_presets.extend(read.build_presets);
+            presets.test_presets.extend(read.test_presets);
+        }
+
+        Ok(presets)
+    }
+
+    /// Names of configure presets that can be used directly
+    pub fn visible(&self) -> Vec<String> {
+        self.configure_presets
+            .iter()
+            .filter(|v| !v.hidden)
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    /// Build directory of configure preset with macros expanded
+    pub fn binary_dir(&self, name: &str) -> Option<String> {
+        // Parent's directory is expanded with name of the child
+        self.unexpanded_binary_dir(name, &mut vec![])
+            .map(|v| expand_preset_macros(&v, name))
+    }
+
+    /// Directory of preset or its closest parent, presets in `visited` stop cyclic inheritance
+    fn unexpanded_binary_dir(&self, name: &str, visited: &mut Vec<String>) -> Option<String> {
+        if visited.iter().any(|v| v == name) {
+            return None;
+        }
+        visited.push(name.to_owned());
+
+        let preset = self.configure_presets.iter().find(|v| v.name == name)?;
+        if let Some(binary_dir) = &preset.binary_dir {
+            return Some(binary_dir.clone());
+        }
+
+        match &preset.inherits {
+            Inherits::None => None,
+            Inherits::One(parent) => self.unexpanded_binary_dir(parent, visited),
+            Inherits::Many(parents) => parents
+                .iter()
+                .find_map(|v| self.unexpanded_binary_dir(v, visited)),
+        }
+    }
+
+    /// First visible build preset using given configure preset
+    pub fn build_preset(&self, configure: &str) -> Option<String> {
+        find_step_preset(&self.build_presets, configure)
+    }
+
+    /// First visible test preset using given configure preset
+    pub fn test_preset(&self, configure: &str) -> Option<String> {
+        find_step_preset(&self.test_presets, configure)
+    }
+}
+
+fn find_step_preset(presets: &[StepPreset], configure: &str) -> Option<String> {
+    presets
+        .iter()
+        .find(|v| !v.hidden && v.configure_preset.as_deref() == Some(configure))
+        .map(|v| v.name.clone())
+}
+
+/// Expands macros that can be used in preset paths. Paths stay relative to project root
+fn expand_preset_macros(input: &str, preset: &str) -> String {
+    input
+        .replace("${sourceDir}/", "")
+        .replace("${sourceDir}", ".")
+        .replace("${sourceParentDir}", "..")
+        .replace("${presetName}", preset)
+}
+
+/// Environment and CMake variables used to select given compiler
+pub fn compiler_variables(compiler: &str) -> (&'static str, &'static str) {
+    if compiler.ends_with("++") {
+        ("CXX", "CMAKE_CXX_COMPILER")
+    } else {
+        ("CC", "CMAKE_C_COMPILER")
+    }
+}
+
+/// Meson array option like `['-fsanitize=address', '-g']`, flags can contain commas
+fn meson_array(values: &[String]) -> String {
+    let values = values
+        .iter()
+        .map(|v| format!("'{}'", v.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
+
+/// Checks if file can be compiled on its own by a C or C++ compiler
+pub fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|v| ["c", "cc", "cpp", "cxx", "c++"].contains(&v))
+}
+
+/// Step compiling single source file into given output
+pub fn compile_file(
+    compiler: &str,
+    source: &Path,
+    output: &Path,
+    flags: &[String],
+    ldflags: &[String],
+) -> Step {
+    let mut step = Step::new(compiler, &[]);
+    step.args.extend(flags.iter().cloned());
+    step.args.push(source.display().to_string());
+    step.args
+        .extend(["-o".to_owned(), output.display().to_string()]);
+    step.args.extend(ldflags.iter().cloned());
+    step
+}
+
+/// Output path for binary built from a single file
+///
+/// Path of the source is hashed so files with the same name don't overwrite each other
+pub fn single_file_output(cache_dir: &Path, source: &Path) -> PathBuf {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    let stem = source
+        .file_stem()
+        .and_then(|v| v.to_str())
+        .unwrap_or("main");
+
+    cache_dir
+        .join("single")
+        .join(format!("{}-{:x}", stem, hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(compiler: &str, build_dir: &str, configured: bool) -> BuildOptions {
+        BuildOptions {
+            compiler: compiler.to_owned(),
+            build_dir: build_dir.to_owned(),
+            configured,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_steps_test() {
+        let mut cmake = options("clang", "build", false);
+        cmake.generator = Some("Ninja".to_owned());
+        assert_eq!(
+            BuildSystem::CMake.build_steps(&cmake),
+            vec![
+                Step::new(
+                    "cmake",
+                    &[
+                        "-B",
+                        "build",
+                        "-G",
+                        "Ninja",
+                        "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON",
+                        "-DCMAKE_C_COMPILER=clang"
+                    ]
+                ),
+                Step::new("cmake", &["--build", "build"]),
+            ]
+        );
+
+        cmake.toolchain_file = Some("/vcpkg/scripts/buildsystems/vcpkg.cmake".to_owned());
+        assert!(BuildSystem::CMake.build_steps(&cmake)[0].args.contains(
+            &"-DCMAKE_TOOLCHAIN_FILE=/vcpkg/scripts/buildsystems/vcpkg.cmake".to_owned()
+        ));
+
+        // Compiler, generator and toolchain of preset are kept
+        let preset = BuildOptions {
+            preset: Some("ci".to_owned()),
+            ..cmake.clone()
+        };
+        assert_eq!(
+            BuildSystem::CMake.build_steps(&preset)[0],
+            Step::new(
+                "cmake",
+                &["--preset", "ci", "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON"]
+            )
+        );
+
+        cmake.configured = true;
+        cmake.build_type = Some(BuildType::Release);
+        cmake.preset = Some("default".to_owned());
+        cmake.build_preset = Some("default-build".to_owned());
+        assert_eq!(
+            BuildSystem::CMake.build_steps(&cmake),
+            vec![
+                Step::new(
+                    "cmake",
+                    &[
+                        "--preset",
+                        "default",
+                        "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON",
+                        "-DCMAKE_BUILD_TYPE=Release"
+                    ]
+                ),
+                Step::new(
+                    "cmake",
+                    &[
+                        "--build",
+                        "--preset",
+                        "default-build",
+                        "--config",
+                        "Release"
+                    ]
+                ),
+            ]
+        );
+
+        assert_eq!(
+            BuildSystem::Meson.build_steps(&options("gcc", "builddir", false))[0],
+            Step::new("meson", &["setup", "builddir"]).env("CC", "gcc")
+        );
+        assert_eq!(
+            BuildSystem::Meson
+                .build_steps(&options("gcc", "builddir", true))
+                .len(),
+            1
+        );
+        assert_eq!(
+            BuildSystem::Make.build_steps(&options("g++", ".", true)),
+            vec![Step::new("make", &["CXX=g++"])]
+        );
+    }
+
+    #[test]
+    fn profile_flags_test() {
+        // Flags of custom profiles reach compiler, not the build tool
+        let flags = vec!["-fsanitize=address".to_owned(), "-Wl,-z,now".to_owned()];
+        let with_flags = |compiler, build_dir, configured| BuildOptions {
+            flags: flags.clone(),
+            ..options(compiler, build_dir, configured)
+        };
+
+        let steps = BuildSystem::CMake.build_steps(&with_flags("g++", "build", false));
+        assert!(steps[0]
+            .args
+            .contains(&"-DCMAKE_CXX_FLAGS=-fsanitize=address -Wl,-z,now".to_owned()));
+        assert_eq!(steps[1], Step::new("cmake", &["--build", "build"]));
+
+        assert_eq!(
+            BuildSystem::Meson.build_steps(&with_flags("gcc", "builddir", true))[0],
+            Step::new(
+                "meson",
+                &[
+                    "configure",
+                    "builddir",
+                    "-Dc_args=['-fsanitize=address', '-Wl,-z,now']"
+                ]
+            )
+        );
+        assert_eq!(
+            BuildSystem::Autotools.build_steps(&with_flags("gcc", ".", false)),
+            vec![
+                Step::new(
+                    "./configure",
+                    &["CC=gcc", "CFLAGS=-fsanitize=address -Wl,-z,now"]
+                ),
+                Step::new("make", &[]),
+            ]
+        );
+        assert_eq!(
+            BuildSystem::Make.build_steps(&with_flags("g++", ".", true)),
+            vec![Step::new(
+                "make",
+                &["CXX=g++", "CXXFLAGS=-fsanitize=address -Wl,-z,now"]
+            )]
+        );
+    }
+
+    #[test]
+    fn autotools_test() -> anyhow::Result<()> {
+        let dir = Path::new("/tmp/ciri/autotools_test");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("configure.ac"), "")?;
+        assert_eq!(BuildSystem::detect(dir), Some(BuildSystem::Autotools));
+        assert!(!BuildSystem::Autotools.is_configured(dir));
+        assert_eq!(
+            BuildSystem::Autotools.bootstrap_step(dir),
+            Some(Step::new("autoreconf", &["--install"]))
+        );
+
+        fs::write(dir.join("autogen.sh"), "")?;
+        let mut autotools = options("gcc", ".", false);
+        autotools.bootstrap = BuildSystem::Autotools.bootstrap_step(dir);
+        autotools.build_type = Some(BuildType::Release);
+        assert_eq!(
+            BuildSystem::Autotools.build_steps(&autotools),
+            vec![
+                Step::new("sh", &["autogen.sh"]),
+                Step::new("./configure", &["CC=gcc", "CFLAGS=-O2"]),
+                Step::new("make", &[]),
+            ]
+        );
+
+        fs::write(dir.join("configure"), "")?;
+        assert_eq!(BuildSystem::Autotools.bootstrap_step(dir), None);
+        assert_eq!(
+            BuildSystem::Autotools.build_steps(&options("g++", ".", true)),
+            vec![Step::new("make", &[])]
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_steps_test() {
+        assert_eq!(
+            BuildSystem::CMake.test_steps(&options("g++", "build", true), Some("unit")),
+            vec![Step::new(
+                "ctest",
+                &["--test-dir", "build", "--output-on-failure", "-R", "unit"]
+            )]
+        );
+        assert_eq!(
+            BuildSystem::Meson.test_steps(&options("gcc", "builddir", true), Some("unit")),
+            vec![Step::new(
+                "meson",
+                &["test", "-C", "builddir", "--print-errorlogs", "unit"]
+            )]
+        );
+        assert!(BuildSystem::Make
+            .test_steps(&options("gcc", ".", true), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn filter_args_test() {
+        assert_eq!(
+            BuildSystem::CMake.filter_args(&["unit", "a.b"]),
+            Some(vec!["-R".to_owned(), "^(unit|a\\.b)$".to_owned()])
+        );
+        assert_eq!(
+            BuildSystem::Meson.filter_args(&["unit", "slow"]),
+            Some(vec!["unit".to_owned(), "slow".to_owned()])
+        );
+        assert_eq!(
+            BuildSystem::Autotools.filter_args(&["a.test", "b.test"]),
+            Some(vec!["TESTS=a.test b.test".to_owned()])
+        );
+        assert_eq!(BuildSystem::Make.filter_args(&["unit"]), None);
+    }
+
+    #[test]
+    fn cmake_presets_test() {
+        let presets: CMakePresets = serde_json::from_str(
+            r#"{
+              "version": 6,
+              "configurePresets": [
+                { "name": "base", "hidden": true, "binaryDir": "${sourceDir}/out/${presetName}" },
+                { "name": "debug", "inherits": "base" },
+                { "name": "release", "inherits": ["base"], "binaryDir": "${sourceDir}/release" }
+              ],
+              "buildPresets": [{ "name": "debug-build", "configurePreset": "debug" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(presets.visible(), vec!["debug", "release"]);
+        assert_eq!(presets.binary_dir("debug"), Some("out/debug".to_owned()));
+        assert_eq!(presets.binary_dir("release"), Some("release".to_owned()));
+        assert_eq!(presets.binary_dir("missing"), None);
+        assert_eq!(
+            presets.build_preset("debug"),
+            Some("debug-build".to_owned())
+        );
+        assert_eq!(presets.test_preset("debug"), None);
+    }
+
+    #[test]
+    fn cmake_presets_cycle_test() {
+        let presets: CMakePresets = serde_json::from_str(
+            r#"{
+              "version": 6,
+              "configurePresets": [
+                { "name": "a", "inherits": "b" },
+                { "name": "b", "inherits": ["c", "a"] },
+                { "name": "c", "inherits": "b" }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(presets.binary_dir("a"), None);
+    }
+
+    #[test]
+    fn compile_file_test() {
+        let step = compile_file(
+            "gcc",
+            Path::new("foo.c"),
+            Path::new("/tmp/foo"),
+            &["-O2".to_owned()],
+            &["-lm".to_owned()],
+        );
+        assert_eq!(
+            step,
+            Step::new("gcc", &["-O2", "foo.c", "-o", "/tmp/foo", "-lm"])
+        );
+
+        assert!(is_source_file(Path::new("foo.c")));
+        assert!(is_source_file(Path::new("src/foo.cpp")));
+        assert!(!is_source_file(Path::new("foo.h")));
+        assert!(!is_source_file(Path::new("foo")));
+    }
+}
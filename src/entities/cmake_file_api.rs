@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{IntoDiagnostic, WrapErr};
+use serde::Deserialize;
+
+const API_DIR: &str = ".cmake/api/v1";
+
+/// Executable target reported by CMake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CMakeTarget {
+    pub name: String,
+    /// Path to built binary relative to project root
+    pub artifact: PathBuf,
+}
+
+#[derive(Deserialize, Debug)]
+struct Index {
+    reply: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CodeModel {
+    configurations: Vec<Configuration>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Configuration {
+    name: String,
+    targets: Vec<TargetRef>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TargetRef {
+    name: String,
+    json_file: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Target {
+    #[serde(rename = "type")]
+    target_type: String,
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Artifact {
+    path: String,
+}
+
+/// Asks CMake to write codemodel reply on next configure
+pub fn write_query(build_dir: &Path) -> miette::Result<()> {
+    let query = build_dir.join(API_DIR).join("query");
+    fs::create_dir_all(&query).into_diagnostic()?;
+    fs::write(query.join("codemodel-v2"), "").into_diagnostic()?;
+    Ok(())
+}
+
+/// Reads executable targets from the newest codemodel reply
+///
+/// Returns `None` when project wasn't configured with a query.
+/// Multi-config generators report every configuration, `config` selects one of them
+pub fn executables(
+    build_dir: &Path,
+    config: Option<&str>,
+) -> miette::Result<Option<Vec<CMakeTarget>>> {
+    let reply = build_dir.join(API_DIR).join("reply");
+    let Ok(entries) = fs::read_dir(&reply) else {
+        return Ok(None);
+    };
+
+    // Index names contain timestamp so the last one is the newest
+    let Some(index) = entries
+        .filter_map(|v| v.ok())
+        .map(|v| v.file_name().to_string_lossy().to_string())
+        .filter(|v| v.starts_with("index-") && v.ends_with(".json"))
+        .max()
+    else {
+        return Ok(None);
+    };
+
+    let index: Index = read_json(&reply.join(index))?;
+    let Some(codemodel) = index
+        .reply
+        .get("codemodel-v2")
+        .and_then(|v| v.get("jsonFile"))
+        .and_then(|v| v.as_str())
+    else {
+        return Ok(None);
+    };
+
+    let codemodel: CodeModel = read_json(&reply.join(codemodel))?;
+    let configuration = codemodel
+        .configurations
+        .iter()
+        .find(|v| config.is_some_and(|config| v.name.eq_ignore_ascii_case(config)))
+        .or(codemodel.configurations.first());
+    let Some(configuration) = configuration else {
+        return Ok(Some(vec![]));
+    };
+
+    let mut executables = vec![];
+    for target_ref in &configuration.targets {
+        let target: Target = read_json(&reply.join(&target_ref.json_file))?;
+        if target.target_type != "EXECUTABLE" {
+            continue;
+        }
+
+        if let Some(artifact) = target.artifacts.first() {
+            executables.push(CMakeTarget {
+                name: target_ref.name.clone(),
+                artifact: build_dir.join(&artifact.path),
+            });
+        }
+    }
+
+    Ok(Some(executables))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> miette::Result<T> {
+    let data = fs::read_to_string(path).into_diagnostic()?;
+    serde_json::from_str(&data)
+        .into_diagnostic()
+        .wrap_err(format!("Failed to parse CMake reply {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executables_test() -> anyhow::Result<()> {
+        let build_dir = Path::new("/tmp/ciri/cmake_file_api_test/build");
+        let reply = build_dir.join(API_DIR).join("reply");
+        fs::create_dir_all(&reply)?;
+
+        assert_eq!(executables(build_dir, None).unwrap(), None);
+
+        fs::write(
+            reply.join("index-2024-01-01T00-00-00-0000.json"),
+            r#"{ "reply": { "codemodel-v2": { "kind": "codemodel", "jsonFile": "codemodel-v2-1.json" } } }"#,
+        )?;
+        fs::write(
+            reply.join("codemodel-v2-1.json"),
+            r#"{ "configurations": [
+                { "name": "Debug", "targets": [
+                    { "name": "app", "jsonFile": "target-app.json" },
+                    { "name": "lib", "jsonFile": "target-lib.json" }
+                ] },
+                { "name": "Release", "targets": [] }
+            ] }"#,
+        )?;
+        fs::write(
+            reply.join("target-app.json"),
+            r#"{ "name": "app", "type": "EXECUTABLE", "artifacts": [{ "path": "bin/app" }] }"#,
+        )?;
+        fs::write(
+            reply.join("target-lib.json"),
+            r#"{ "name": "lib", "type": "STATIC_LIBRARY", "artifacts": [{ "path": "liblib.a" }] }"#,
+        )?;
+
+        assert_eq!(
+            executables(build_dir, None).unwrap(),
+            Some(vec![CMakeTarget {
+                name: "app".to_owned(),
+                artifact: build_dir.join("bin/app"),
+            }])
+        );
+        assert_eq!(
+            executables(build_dir, Some("Release")).unwrap(),
+            Some(vec![])
+        );
+
+        fs::remove_dir_all("/tmp/ciri/cmake_file_api_test")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use miette::{miette, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+use crate::parsers::dotenv::{dotenv_parser, expand, unquoted, EnvPart};
+
+/// Entry of `[env]` table in `.ciri.toml`, nested tables are environment profiles
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EnvEntry {
+    Value(String),
+    Profile(HashMap<String, String>),
+}
+
+const SECRET_MARKERS: [&str; 8] = [
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "PRIVATE",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Files read for environment profile, later ones override earlier
+pub fn env_files(profile: Option<&str>) -> Vec<String> {
+    let mut files = vec![".env".to_owned(), ".env.local".to_owned()];
+    if let Some(profile) = profile {
+        files.push(format!(".env.{}", profile));
+    }
+    files
+}
+
+/// Loads variables from `.env` files in `dir` and `[env]` table of config
+///
+/// Values are overridden in order: `.env`, `.env.local`, `.env.<profile>`, `[env]`,
+/// `[env.<profile>]`. Variables already set in `inherited` environment win over all of them
+pub fn load_env(
+    dir: &Path,
+    profile: Option<&str>,
+    config: &HashMap<String, EnvEntry>,
+    inherited: impl Fn(&str) -> Option<String>,
+) -> miette::Result<Vec<(String, String)>> {
+    let mut vars: Vec<(String, String)> = vec![];
+    let set = |key: String, parts: Vec<EnvPart>, vars: &mut Vec<(String, String)>| {
+        let value = expand(&parts, |name| {
+            inherited(name).or_else(|| {
+                vars.iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.to_owned())
+            })
+        });
+        vars.retain(|(k, _)| *k != key);
+        vars.push((key, value));
+    };
+
+    let mut found_profile = false;
+    for (i, file) in env_files(profile).iter().enumerate() {
+        let path = dir.join(file);
+        if !path.exists() {
+            continue;
+        }
+        found_profile |= i == 2;
+
+        let input = fs::read_to_string(&path).into_diagnostic()?;
+        let (_, parsed) =
+            dotenv_parser(&input).map_err(|e| miette!("Failed to parse {}: {}", file, e))?;
+        for (key, parts) in parsed {
+            set(key, parts, &mut vars);
+        }
+    }
+
+    let table = |entries: Vec<(&String, &String)>,
+                 vars: &mut Vec<(String, String)>|
+     -> miette::Result<()> {
+        for (key, value) in entries {
+            let (_, parts) =
+                unquoted(value).map_err(|e| miette!("Invalid value of {}: {}", key, e))?;
+            set(key.to_owned(), parts, vars);
+        }
+        Ok(())
+    };
+
+    let mut values = config
+        .iter()
+        .filter_map(|(k, v)| match v {
+            EnvEntry::Value(v) => Some((k, v)),
+            EnvEntry::Profile(_) => None,
+        })
+        .collect::<Vec<_>>();
+    values.sort();
+    table(values, &mut vars)?;
+
+    if let Some(profile) = profile {
+        if let Some(EnvEntry::Profile(entries)) = config.get(profile) {
+            found_profile = true;
+            let mut entries = entries.iter().collect::<Vec<_>>();
+            entries.sort();
+            table(entries, &mut vars)?;
+        }
+
+        if !found_profile {
+            return Err(miette!(
+                help = format!(
+                    "Create .env.{} or add [env.{}] to .ciri.toml",
+                    profile, profile
+                ),
+                "Environment profile \"{}\" wasn't found",
+                profile
+            ));
+        }
+    }
+
+    vars.retain(|(k, _)| inherited(k).is_none());
+    Ok(vars)
+}
+
+/// Whether variable name looks like it holds a secret
+pub fn is_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_MARKERS.iter().any(|v| key.contains(v))
+}
+
+/// Value safe to print, secrets are replaced with asterisks
+pub fn masked(key: &str, value: &str) -> String {
+    if is_secret(key) && !value.is_empty() {
+        "********".to_owned()
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_env_test() -> anyhow::Result<()> {
+        let dir = Path::new("/tmp/ciri/env_test");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(".env"),
+            "HOST=localhost\nPORT=80\nSHELL_VAR=file\n",
+        )?;
+        fs::write(dir.join(".env.local"), "PORT=8080\n")?;
+        fs::write(dir.join(".env.staging"), "HOST=staging.local\n")?;
+
+        let config: HashMap<String, EnvEntry> = toml::from_str(
+            r#"
+            URL = "http://${HOST}:${PORT}"
+            API_TOKEN = "abc"
+
+            [staging]
+            PORT = "443"
+            "#,
+        )?;
+        let inherited = |name: &str| (name == "SHELL_VAR").then(|| "shell".to_owned());
+
+        let vars = load_env(dir, None, &config, inherited).unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("HOST".to_owned(), "localhost".to_owned()),
+                ("PORT".to_owned(), "8080".to_owned()),
+                ("API_TOKEN".to_owned(), "abc".to_owned()),
+                ("URL".to_owned(), "http://localhost:8080".to_owned()),
+            ]
+        );
+
+        let vars = load_env(dir, Some("staging"), &config, inherited).unwrap();
+        assert!(vars.contains(&("URL".to_owned(), "http://staging.local:8080".to_owned())));
+        assert!(vars.contains(&("PORT".to_owned(), "443".to_owned())));
+
+        let err = load_env(dir, Some("missing"), &config, inherited).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Environment profile \"missing\" wasn't found"
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn masked_test() {
+        assert_eq!(masked("API_TOKEN", "abc"), "********");
+        assert_eq!(masked("db_password", "abc"), "********");
+        assert_eq!(masked("HOST", "localhost"), "localhost");
+        assert_eq!(masked("SECRET", ""), "");
+    }
+}
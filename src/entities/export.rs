@@ -0,0 +1,435 @@
+//! Machine-readable results written by `--report` of `ciri test` and `ciri build`
+//!
+//! Schema of both formats is described in `docs/reports.md`
+
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use miette::{IntoDiagnostic, WrapErr};
+use serde::Serialize;
+
+use super::test_report::{TestCase, TestReport, TestStatus};
+use crate::parsers::compiler::{CompilerMessage, Level};
+
+/// Version of JSON schema, raised when fields are removed or change meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Json,
+}
+
+/// Report requested with `--report format=path`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl ReportTarget {
+    pub fn write(&self, content: &str) -> miette::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|v| !v.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+        fs::write(&self.path, content)
+            .into_diagnostic()
+            .wrap_err(format!("Report \"{}\" wasn't written", self.path.display()))
+    }
+}
+
+/// Parses target like `junit=report.xml` or `json=report.json`
+pub fn parse_report_target(input: &str) -> Result<ReportTarget, String> {
+    let Some((format, path)) = input.split_once('=') else {
+        return Err(format!(
+            "\"{}\" isn't a report, use junit=<path> or json=<path>",
+            input
+        ));
+    };
+    let format = match format.trim() {
+        "junit" => ReportFormat::Junit,
+        "json" => ReportFormat::Json,
+        format => {
+            return Err(format!(
+                "Unknown report format \"{}\", use junit or json",
+                format
+            ))
+        }
+    };
+    if path.is_empty() {
+        return Err("Path of report is missing".to_owned());
+    }
+
+    Ok(ReportTarget {
+        format,
+        path: PathBuf::from(path),
+    })
+}
+
+#[derive(Serialize)]
+struct TestTotals {
+    tests: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    flaky: usize,
+}
+
+#[derive(Serialize)]
+struct TestExport<'a> {
+    version: u32,
+    kind: &'static str,
+    success: bool,
+    totals: TestTotals,
+    cases: &'a [TestCase],
+}
+
+#[derive(Serialize)]
+struct BuildTotals {
+    errors: usize,
+    warnings: usize,
+    notes: usize,
+}
+
+#[derive(Serialize)]
+struct BuildExport<'a> {
+    version: u32,
+    kind: &'static str,
+    success: bool,
+    totals: BuildTotals,
+    messages: &'a [CompilerMessage],
+}
+
+/// Report of tests in `format`
+pub fn test_report(format: ReportFormat, report: &TestReport, success: bool) -> String {
+    match format {
+        ReportFormat::Junit => junit(report),
+        ReportFormat::Json => {
+            let export = TestExport {
+                version: SCHEMA_VERSION,
+                kind: "test",
+                success,
+                totals: TestTotals {
+                    tests: report.cases.len(),
+                    passed: report.count(TestStatus::Passed),
+                    failed: report.count(TestStatus::Failed),
+                    skipped: report.count(TestStatus::Skipped),
+                    flaky: report.cases.iter().filter(|v| v.flaky).count(),
+                },
+                cases: &report.cases,
+            };
+            serde_json::to_string_pretty(&export).unwrap_or_default()
+        }
+    }
+}
+
+/// Report of build in `format`, errors are failed cases in JUnit
+pub fn build_report(format: ReportFormat, messages: &[CompilerMessage], success: bool) -> String {
+    let count = |level| messages.iter().filter(|v| v.level == level).count();
+    match format {
+        ReportFormat::Junit => junit(&build_cases(messages, success)),
+        ReportFormat::Json => {
+            let export = BuildExport {
+                version: SCHEMA_VERSION,
+                kind: "build",
+                success,
+                totals: BuildTotals {
+                    errors: count(Level::Error),
+                    warnings: count(Level::Warning),
+                    notes: count(Level::Note),
+                },
+                messages,
+            };
+            serde_json::to_string_pretty(&export).unwrap_or_default()
+        }
+    }
+}
+
+/// Errors as failed cases, a build without them is a single case
+fn build_cases(messages: &[CompilerMessage], success: bool) -> TestReport {
+    let mut cases: Vec<TestCase> = messages
+        .iter()
+        .filter(|v| v.level == Level::Error)
+        .map(|message| {
+            let name = match &message.location {
+                Some(v) => format!("{}:{}:{}", v.file.display(), v.line, v.column),
+                None => message.code.clone().unwrap_or("error".to_owned()),
+            };
+            let output = [Some(&message.message), message.help.as_ref()]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n");
+            TestCase {
+                suite: Some("build".to_owned()),
+                output: Some(output),
+                ..TestCase::new(&name, TestStatus::Failed)
+            }
+        })
+        .collect();
+
+    if cases.is_empty() {
+        let status = if success {
+            TestStatus::Passed
+        } else {
+            TestStatus::Failed
+        };
+        cases.push(TestCase {
+            suite: Some("build".to_owned()),
+            ..TestCase::new("build", status)
+        });
+    }
+    TestReport { cases }
+}
+
+/// Escapes text for XML, control characters like colors of terminal aren't allowed there
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Text of terminal without color escape sequences
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Sequences like `\x1b[1;31m` end with a letter
+            chars.by_ref().find(|v| v.is_ascii_alphabetic());
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn junit(report: &TestReport) -> String {
+    let mut suites: Vec<(&str, Vec<&TestCase>)> = vec![];
+    for case in &report.cases {
+        let name = case.suite.as_deref().unwrap_or("tests");
+        match suites.iter_mut().find(|(v, _)| *v == name) {
+            Some((_, cases)) => cases.push(case),
+            None => suites.push((name, vec![case])),
+        }
+    }
+
+    // Sum of no floats is negative zero
+    let time = |cases: &[&TestCase]| {
+        cases
+            .iter()
+            .filter_map(|v| v.duration)
+            .fold(0.0, |a, b| a + b)
+    };
+    let count = |cases: &[&TestCase], status| cases.iter().filter(|v| v.status == status).count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let all: Vec<&TestCase> = report.cases.iter().collect();
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"ciri\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        all.len(),
+        count(&all, TestStatus::Failed),
+        count(&all, TestStatus::Skipped),
+        time(&all)
+    );
+    for (name, cases) in suites {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+            escape_xml(name),
+            cases.len(),
+            count(&cases, TestStatus::Failed),
+            count(&cases, TestStatus::Skipped),
+            time(&cases)
+        );
+        for case in cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape_xml(&case.name),
+                escape_xml(name)
+            );
+            if let Some(duration) = case.duration {
+                let _ = write!(xml, " time=\"{:.3}\"", duration);
+            }
+
+            let output = case.output.as_deref().map(strip_ansi).unwrap_or_default();
+            let message = escape_xml(output.lines().next().unwrap_or_default());
+            let element = match case.status {
+                TestStatus::Failed => "failure",
+                TestStatus::Skipped => "skipped",
+                TestStatus::Passed if case.flaky => "flakyFailure",
+                TestStatus::Passed => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+            };
+            if output.is_empty() {
+                let _ = writeln!(xml, ">\n      <{}/>\n    </testcase>", element);
+                continue;
+            }
+            let _ = writeln!(
+                xml,
+                ">\n      <{} message=\"{}\">{}</{}>\n    </testcase>",
+                element,
+                message,
+                escape_xml(&output),
+                element
+            );
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::parsers::compiler::parse_messages;
+    use crate::parsers::compiler::OutputFormat;
+    use crate::parsers::junit::parse_junit;
+
+    fn report() -> TestReport {
+        TestReport {
+            cases: vec![
+                TestCase {
+                    suite: Some("src/lib.rs".to_owned()),
+                    duration: Some(0.25),
+                    ..TestCase::new("a", TestStatus::Passed)
+                },
+                TestCase {
+                    suite: Some("src/lib.rs".to_owned()),
+                    output: Some("\u{1b}[31massert <1> & \"2\"\u{1b}[0m\nat line 3".to_owned()),
+                    ..TestCase::new("b", TestStatus::Failed)
+                },
+                TestCase {
+                    output: Some("first try".to_owned()),
+                    flaky: true,
+                    ..TestCase::new("c", TestStatus::Passed)
+                },
+                TestCase::new("d", TestStatus::Skipped),
+            ],
+        }
+    }
+
+    #[test]
+    fn parse_report_target_test() {
+        assert_eq!(
+            parse_report_target("junit=out/report.xml"),
+            Ok(ReportTarget {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("out/report.xml")
+            })
+        );
+        assert_eq!(
+            parse_report_target("json=r.json").map(|v| v.format),
+            Ok(ReportFormat::Json)
+        );
+        assert!(parse_report_target("report.xml").is_err());
+        assert!(parse_report_target("html=r.html").is_err());
+        assert!(parse_report_target("json=").is_err());
+    }
+
+    #[test]
+    fn test_json_test() {
+        let json: serde_json::Value =
+            serde_json::from_str(&test_report(ReportFormat::Json, &report(), false)).unwrap();
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["kind"], "test");
+        assert_eq!(json["success"], false);
+        assert_eq!(
+            json["totals"],
+            serde_json::json!({"tests": 4, "passed": 2, "failed": 1, "skipped": 1, "flaky": 1})
+        );
+        assert_eq!(
+            json["cases"][0],
+            serde_json::json!({
+                "name": "a",
+                "suite": "src/lib.rs",
+                "status": "passed",
+                "duration": 0.25,
+                "output": null,
+                "flaky": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_junit_test() -> miette::Result<()> {
+        let xml = test_report(ReportFormat::Junit, &report(), false);
+        assert!(xml.contains(
+            "<failure message=\"assert &lt;1&gt; &amp; &quot;2&quot;\">assert &lt;1&gt; &amp; &quot;2&quot;\nat line 3</failure>"
+        ));
+        assert!(xml.contains("<flakyFailure message=\"first try\">first try</flakyFailure>"));
+        assert!(xml.contains("<skipped/>"));
+        assert!(xml.contains("<testsuite name=\"tests\" tests=\"2\" failures=\"0\" errors=\"0\" skipped=\"1\" time=\"0.000\">"));
+
+        // What ciri writes it can read back
+        let parsed = parse_junit(&xml)?;
+        assert_eq!(parsed.to_string(), "2 passed, 1 failed, 1 skipped");
+        assert_eq!(parsed.cases[0].full_name(), "src/lib.rs::a");
+        assert_eq!(parsed.cases[0].duration, Some(0.25));
+        assert_eq!(parsed.cases[2].full_name(), "tests::c");
+        assert_eq!(
+            parsed.cases[1].output.as_deref(),
+            Some("assert <1> & \"2\"\nat line 3")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn build_report_test() -> miette::Result<()> {
+        let messages = parse_messages(
+            OutputFormat::Gcc,
+            "main.c:3:5: error: unknown type name 'foo'\nmain.c:1:1: warning: unused [-Wunused]",
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&build_report(ReportFormat::Json, &messages, false)).unwrap();
+        assert_eq!(json["kind"], "build");
+        assert_eq!(
+            json["totals"],
+            serde_json::json!({"errors": 1, "warnings": 1, "notes": 0})
+        );
+        assert_eq!(json["messages"][1]["code"], "-Wunused");
+        assert_eq!(json["messages"][0]["location"]["file"], "main.c");
+
+        let parsed = parse_junit(&build_report(ReportFormat::Junit, &messages, false))?;
+        assert_eq!(parsed.cases.len(), 1);
+        assert_eq!(parsed.cases[0].full_name(), "build::main.c:3:5");
+        assert_eq!(
+            parsed.cases[0].output.as_deref(),
+            Some("unknown type name 'foo'")
+        );
+
+        let parsed = parse_junit(&build_report(ReportFormat::Junit, &[], true))?;
+        assert_eq!(parsed.to_string(), "1 passed, 0 failed, 0 skipped");
+
+        let target = ReportTarget {
+            format: ReportFormat::Json,
+            path: PathBuf::from("/tmp/ciri/build_report_test/out/r.json"),
+        };
+        let _ = fs::remove_dir_all("/tmp/ciri/build_report_test");
+        target.write("{}")?;
+        assert!(Path::new("/tmp/ciri/build_report_test/out/r.json").exists());
+        fs::remove_dir_all("/tmp/ciri/build_report_test").into_diagnostic()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use miette::{miette, IntoDiagnostic};
+use sha2::{Digest, Sha256};
+
+use crate::cache_dir;
+
+/// Output directories skipped by wildcards, unless pattern names them
+const IGNORED_DIRS: [&str; 6] = [
+    "build",
+    "builddir",
+    "dist",
+    "node_modules",
+    "target",
+    ".build",
+];
+
+/// Inputs of manager used when `build_inputs` isn't set in `.ciri.toml`
+pub fn default_inputs(manager: &str) -> &'static [&'static str] {
+    match manager {
+        "gcc" | "clang" => &["**/*.c", "**/*.h"],
+        "g++" | "clang++" => &[
+            "**/*.c", "**/*.cc", "**/*.cpp", "**/*.cxx", "**/*.c++", "**/*.h", "**/*.hpp",
+        ],
+        "cargo" => &["Cargo.toml", "build.rs", "src/**/*.rs"],
+        "npm" | "pnpm" | "yarn" | "bun" => &["package.json", "src/**/*"],
+        "go" => &["go.mod", "**/*.go"],
+        "lua" => &["*.rockspec", "**/*.lua"],
+        "swift" => &["Package.swift", "Sources/**/*.swift"],
+        _ => &[],
+    }
+}
+
+/// Hash of files matched by globs relative to `dir` and of `extra` values like flags
+///
+/// Paths and contents are hashed, so touching a file without changing it keeps the fingerprint
+pub fn fingerprint(dir: &Path, patterns: &[String], extra: &[String]) -> miette::Result<String> {
+    let options = MatchOptions {
+        require_literal_leading_dot: true,
+        ..Default::default()
+    };
+
+    let mut files = BTreeSet::new();
+    for pattern in patterns {
+        let full = dir.join(pattern).display().to_string();
+        let paths = glob::glob_with(&full, options)
+            .map_err(|e| miette!("Invalid build input \"{}\": {}", pattern, e))?;
+        for path in paths.filter_map(Result::ok).filter(|v| v.is_file()) {
+            let relative = path.strip_prefix(dir).unwrap_or(&path).to_owned();
+            let ignored = relative.components().any(|v| {
+                let v = v.as_os_str().to_string_lossy();
+                IGNORED_DIRS.contains(&v.as_ref()) && !pattern.contains(v.as_ref())
+            });
+            if !ignored {
+                files.insert(relative);
+            }
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    for value in extra {
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    }
+    for file in files {
+        let content = fs::read(dir.join(&file)).into_diagnostic()?;
+        hasher.update(file.display().to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(content);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|v| format!("{:02x}", v)).collect()
+}
+
+/// Short hash of project path, used to name files that belong to project
+pub fn project_key(project: &Path) -> String {
+    hex(&Sha256::digest(project.display().to_string().as_bytes())[..8])
+}
+
+/// Fingerprints of last successful builds of a project, stored in cache directory
+pub struct Fingerprints {
+    path: PathBuf,
+    entries: HashMap<String, String>,
+}
+
+impl Fingerprints {
+    pub fn load(project: &Path) -> miette::Result<Self> {
+        Self::at(
+            cache_dir()?
+                .join("fingerprints")
+                .join(format!("{}.json", project_key(project))),
+        )
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> miette::Result<Self> {
+        let path = path.into();
+        let entries = if path.exists() {
+            // Broken store only means everything gets built again
+            serde_json::from_str(&fs::read_to_string(&path).into_diagnostic()?).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Whether step was last built from the same inputs
+    pub fn is_fresh(&self, step: &str, fingerprint: &str) -> bool {
+        self.entries.get(step).is_some_and(|v| v == fingerprint)
+    }
+
+    pub fn save(&mut self, step: &str, fingerprint: String) -> miette::Result<()> {
+        self.entries.insert(step.to_owned(), fingerprint);
+        self.write()
+    }
+
+    /// Forgets every step, so next builds run again
+    pub fn clear(&mut self) -> miette::Result<()> {
+        self.entries.clear();
+        if self.path.exists() {
+            fs::remove_file(&self.path).into_diagnostic()?;
+        }
+        Ok(())
+    }
+
+    fn write(&self) -> miette::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+        fs::write(
+            &self.path,
+            serde_json::to_string(&self.entries).into_diagnostic()?,
+        )
+        .into_diagnostic()
+    }
+}
+
+/// Escapes path, so it can be used as build input that matches only itself
+pub fn literal_input(path: &Path) -> String {
+    Pattern::escape(&path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_test() -> miette::Result<()> {
+        let dir = Path::new("/tmp/ciri/fingerprint_test");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir.join("src")).into_diagnostic()?;
+        fs::create_dir_all(dir.join("build")).into_diagnostic()?;
+        fs::write(dir.join("src/main.c"), "int main() {}").into_diagnostic()?;
+        fs::write(dir.join("src/util.h"), "").into_diagnostic()?;
+
+        let inputs = vec!["**/*.c".to_owned(), "**/*.h".to_owned()];
+        let first = fingerprint(dir, &inputs, &[])?;
+        assert_eq!(first.len(), 64);
+        assert_eq!(fingerprint(dir, &inputs, &[])?, first);
+
+        // Outputs and unrelated files don't matter
+        fs::write(dir.join("build/gen.c"), "").into_diagnostic()?;
+        fs::write(dir.join("README.md"), "").into_diagnostic()?;
+        assert_eq!(fingerprint(dir, &inputs, &[])?, first);
+
+        // Flags are part of it
+        assert_ne!(fingerprint(dir, &inputs, &["-O2".to_owned()])?, first);
+
+        fs::write(dir.join("src/util.h"), "#define X").into_diagnostic()?;
+        assert_ne!(fingerprint(dir, &inputs, &[])?, first);
+
+        // Paths with glob characters match only themselves
+        fs::write(dir.join("src/[a].c"), "").into_diagnostic()?;
+        let file = literal_input(&dir.join("src/[a].c"));
+        assert_eq!(
+            fingerprint(dir, &[file], &[])?,
+            fingerprint(dir, &["src/?a?.c".to_owned()], &[])?
+        );
+        assert!(fingerprint(dir, &["[".to_owned()], &[]).is_err());
+
+        fs::remove_dir_all(dir).into_diagnostic()?;
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprints_test() -> miette::Result<()> {
+        let path = "/tmp/ciri/fingerprints_test/project.json";
+        let _ = fs::remove_dir_all("/tmp/ciri/fingerprints_test");
+
+        let mut fingerprints = Fingerprints::at(path)?;
+        assert!(!fingerprints.is_fresh("build", "abc"));
+        fingerprints.save("build", "abc".to_owned())?;
+
+        let mut fingerprints = Fingerprints::at(path)?;
+        assert!(fingerprints.is_fresh("build", "abc"));
+        assert!(!fingerprints.is_fresh("build", "def"));
+        assert!(!fingerprints.is_fresh("pre_build", "abc"));
+
+        fingerprints.clear()?;
+        assert!(!Fingerprints::at(path)?.is_fresh("build", "abc"));
+
+        fs::write(path, "{broken").into_diagnostic()?;
+        assert!(!Fingerprints::at(path)?.is_fresh("build", "abc"));
+        fs::remove_dir_all("/tmp/ciri/fingerprints_test").into_diagnostic()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use miette::IntoDiagnostic;
+use serde::{Deserialize, Serialize};
+
+use crate::state_dir;
+
+/// Number of runs shown in trend of a command
+pub const TREND_LENGTH: usize = 8;
+
+/// Build, run or test invocation stored in history
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Absolute path of project directory
+    pub project: PathBuf,
+    /// Detected package managers, comma separated
+    pub manager: String,
+    pub command: String,
+    /// Seconds
+    pub duration: f64,
+    pub exit_code: i32,
+    /// Seconds since unix epoch
+    pub timestamp: u64,
+}
+
+impl HistoryEntry {
+    /// Entry of invocation that finished just now
+    pub fn new(
+        project: PathBuf,
+        manager: String,
+        command: &str,
+        duration: f64,
+        exit_code: i32,
+    ) -> Self {
+        Self {
+            project,
+            manager,
+            command: command.to_owned(),
+            duration,
+            exit_code,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |v| v.as_secs()),
+        }
+    }
+}
+
+/// History stored as JSON lines, so recording only appends to it
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// History in `$XDG_STATE_HOME/ciri`
+    pub fn open() -> miette::Result<Self> {
+        Ok(Self::at(state_dir()?.join("history.jsonl")))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) -> miette::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+
+        let mut line = serde_json::to_string(entry).into_diagnostic()?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .into_diagnostic()?
+            .write_all(line.as_bytes())
+            .into_diagnostic()
+    }
+
+    /// Entries from oldest to newest, lines that can't be parsed are skipped
+    pub fn entries(&self) -> miette::Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        Ok(fs::read_to_string(&self.path)
+            .into_diagnostic()?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Duration of entry compared to earlier successful runs of same command in same project
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trend {
+    /// Relative change from previous run, `0.5` means 50% slower
+    pub change: Option<f64>,
+    /// Last durations up to this run, oldest first
+    pub recent: Vec<f64>,
+}
+
+impl Trend {
+    /// Recent durations as bars like `▁▃█`, scaled between fastest and slowest run
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let min = self.recent.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = self.recent.iter().cloned().fold(0.0, f64::max);
+
+        self.recent
+            .iter()
+            .map(|v| {
+                if max - min <= 0.0 {
+                    BARS[3]
+                } else {
+                    BARS[((v - min) / (max - min) * 7.0).round() as usize]
+                }
+            })
+            .collect()
+    }
+}
+
+/// Trend of every entry, which must be ordered from oldest
+///
+/// Failed runs often stop early, so they aren't compared and don't change trends of later runs
+pub fn trends(entries: &[HistoryEntry]) -> Vec<Trend> {
+    let mut runs: HashMap<(&Path, &str), Vec<f64>> = HashMap::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let runs = runs.entry((&entry.project, &entry.command)).or_default();
+            if entry.exit_code != 0 {
+                return Trend::default();
+            }
+
+            let change = runs
+                .last()
+                .filter(|v| **v > 0.0)
+                .map(|last| entry.duration / last - 1.0);
+            runs.push(entry.duration);
+            Trend {
+                change,
+                recent: runs[runs.len().saturating_sub(TREND_LENGTH)..].to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Unix timestamp as UTC date and time, e.g. `2024-01-31 12:05`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Days to civil date, from Howard Hinnant's algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(project: &str, command: &str, duration: f64, exit_code: i32) -> HistoryEntry {
+        HistoryEntry {
+            project: PathBuf::from(project),
+            manager: "cargo".to_owned(),
+            command: command.to_owned(),
+            duration,
+            exit_code,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn history_test() -> miette::Result<()> {
+        let path = "/tmp/ciri/history_test/history.jsonl";
+        let _ = fs::remove_dir_all("/tmp/ciri/history_test");
+        let history = History::at(path);
+        assert_eq!(history.entries()?, vec![]);
+
+        history.record(&entry("/a", "build", 1.5, 0))?;
+        fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .into_diagnostic()?
+            .write_all(b"{broken\n")
+            .into_diagnostic()?;
+        history.record(&entry("/a", "test", 2.0, 101))?;
+
+        assert_eq!(
+            history.entries()?,
+            vec![entry("/a", "build", 1.5, 0), entry("/a", "test", 2.0, 101)]
+        );
+        fs::remove_dir_all("/tmp/ciri/history_test").into_diagnostic()?;
+        Ok(())
+    }
+
+    #[test]
+    fn trends_test() {
+        let entries = vec![
+            entry("/a", "build", 2.0, 0),
+            entry("/b", "build", 10.0, 0),
+            entry("/a", "test", 5.0, 0),
+            entry("/a", "build", 0.5, 1),
+            entry("/a", "build", 3.0, 0),
+            entry("/a", "build", 1.5, 0),
+        ];
+
+        let trends = trends(&entries);
+        assert_eq!(trends[0].change, None);
+        assert_eq!(trends[1].change, None);
+        assert_eq!(trends[3], Trend::default());
+        assert_eq!(trends[4].change, Some(0.5));
+        assert_eq!(trends[5].change, Some(-0.5));
+        assert_eq!(trends[5].recent, vec![2.0, 3.0, 1.5]);
+        assert_eq!(trends[5].sparkline(), "▃█▁");
+        assert_eq!(trends[0].sparkline(), "▄");
+    }
+
+    #[test]
+    fn format_timestamp_test() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1706702700), "2024-01-31 12:05");
+    }
+}
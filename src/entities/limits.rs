@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use miette::miette;
+use serde::{Deserialize, Serialize};
+
+use crate::Config;
+
+/// Defaults from `[limits]` table of `.ciri.toml`, flags override them
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LimitsConfig {
+    /// Wall time, e.g. `30s`, `5m`
+    pub timeout: Option<String>,
+    /// Memory of a process, e.g. `512M`, `2G`
+    pub memory_limit: Option<String>,
+    /// CPU time, e.g. `1m`
+    pub cpu_time: Option<String>,
+}
+
+/// Limits applied to processes started by run and test
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub timeout: Option<Duration>,
+    /// Bytes of data segment (`RLIMIT_DATA`), which includes heap and anonymous mappings
+    pub memory: Option<u64>,
+    /// Rounded up to whole seconds (`RLIMIT_CPU`)
+    pub cpu_time: Option<Duration>,
+}
+
+impl ResourceLimits {
+    /// Limits from flags with config defaults for ones that weren't given
+    pub fn load(
+        timeout: Option<Duration>,
+        memory: Option<u64>,
+        cpu_time: Option<Duration>,
+    ) -> miette::Result<Self> {
+        let config = Config::load()?.limits.unwrap_or_default();
+        Self {
+            timeout,
+            memory,
+            cpu_time,
+        }
+        .with_config(&config)
+    }
+
+    pub fn with_config(self, config: &LimitsConfig) -> miette::Result<Self> {
+        let invalid =
+            |key: &str, e: String| miette!("Invalid {} in [limits] of .ciri.toml: {}", key, e);
+        let timeout = match (self.timeout, &config.timeout) {
+            (None, Some(v)) => Some(parse_duration(v).map_err(|e| invalid("timeout", e))?),
+            (v, _) => v,
+        };
+        let memory = match (self.memory, &config.memory_limit) {
+            (None, Some(v)) => Some(parse_size(v).map_err(|e| invalid("memory_limit", e))?),
+            (v, _) => v,
+        };
+        let cpu_time = match (self.cpu_time, &config.cpu_time) {
+            (None, Some(v)) => Some(parse_duration(v).map_err(|e| invalid("cpu_time", e))?),
+            (v, _) => v,
+        };
+
+        Ok(Self {
+            timeout,
+            memory,
+            cpu_time,
+        })
+    }
+}
+
+/// Parses duration like `500ms`, `30s`, `1.5m` or `2h`, plain numbers are seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("\"{}\" isn't a duration", input))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        unit => return Err(format!("Unknown time unit \"{}\", use ms, s, m or h", unit)),
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Parses size like `512M`, `2GiB` or `1024`, units are powers of 1024
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("\"{}\" isn't a size", input))?;
+    let unit = unit.trim().to_uppercase();
+    let power = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return Err(format!("Unknown size unit \"{}\", use K, M, G or T", unit)),
+    };
+    Ok((number * 1024f64.powi(power)) as u64)
+}
+
+/// Size in the largest unit that keeps it above one, e.g. `1.5 GiB`
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("5 days").is_err());
+        assert!(parse_duration("s").is_err());
+
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("512M"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_size("2GiB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1.5kb"), Ok(1536));
+        assert!(parse_size("1X").is_err());
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn with_config_test() {
+        let config: LimitsConfig = toml::from_str(
+            r#"
+            timeout = "5m"
+            memory_limit = "1G"
+            "#,
+        )
+        .unwrap();
+
+        let limits = ResourceLimits {
+            timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        }
+        .with_config(&config)
+        .unwrap();
+        assert_eq!(
+            limits,
+            ResourceLimits {
+                timeout: Some(Duration::from_secs(10)),
+                memory: Some(1024 * 1024 * 1024),
+                cpu_time: None,
+            }
+        );
+
+        let config = LimitsConfig {
+            cpu_time: Some("forever".to_owned()),
+            ..Default::default()
+        };
+        assert!(ResourceLimits::default().with_config(&config).is_err());
+    }
+}
@@ -19,6 +19,30 @@ pub struct Manager {
     pub new: Option<&'static str>,
 }
 
+impl Manager {
+    /// Separator some managers need before arguments meant for the program they run
+    pub fn args_separator(&self, subcommand: &str) -> Option<&'static str> {
+        match (self.agent, subcommand) {
+            ("cargo", "run" | "test") => Some("--"),
+            ("npm", "build" | "run" | "test") => Some("--"),
+            ("scala-cli", "run" | "test") => Some("--"),
+            _ => None,
+        }
+    }
+
+    /// Appends extra arguments of subcommand to command arguments
+    pub fn push_args<'a>(&self, subcommand: &str, p: &mut Vec<&'a str>, args: &'a [String]) {
+        if args.is_empty() {
+            return;
+        }
+
+        if let Some(separator) = self.args_separator(subcommand) {
+            p.push(separator);
+        }
+        p.extend(args.iter().map(String::as_str));
+    }
+}
+
 pub const CARGO_MANAGER: Manager = Manager {
     agent: "cargo",
     build: Some("cargo build"),
@@ -109,16 +133,17 @@ pub const BUN_MANAGER: Manager = Manager {
     new: Some("bun init"),
 };
 
+/// Build and run commands depend on the detected [`crate::entities::build_system::BuildSystem`]
 pub const GPP_MANAGER: Manager = Manager {
     agent: "g++",
-    build: Some("cmake -Bbuild && make -Cbuild"),
+    build: None,
     doc: None,
-    clean: Some("rm -r build/"),
-    run: Some("./build/"),
-    default_exec: Some("./build/"),
+    clean: None,
+    run: None,
+    default_exec: None,
     remove: "vcpkg remove",
     remove_global: "vcpkg remove",
-    add: "vcpkg install",
+    add: "vcpkg add port",
     add_global: "vcpkg install",
     test: None,
     search: "vcpkg search",
@@ -126,3 +151,202 @@ pub const GPP_MANAGER: Manager = Manager {
     execute: None,
     new: None,
 };
+
+pub const LUAROCKS_MANAGER: Manager = Manager {
+    agent: "luarocks",
+    build: Some("luarocks make --local"),
+    doc: Some("luarocks doc"),
+    clean: None,
+    run: Some("lua"),
+    default_exec: Some("lua"),
+    remove: "luarocks remove --local",
+    remove_global: "luarocks remove",
+    add: "luarocks install --local",
+    add_global: "luarocks install",
+    test: Some("luarocks test"),
+    search: "luarocks search",
+    upgrade: "luarocks install --local",
+    execute: None,
+    new: None,
+};
+
+/// Dependencies are edited in build.sbt, coursier only installs applications
+pub const SBT_MANAGER: Manager = Manager {
+    agent: "sbt",
+    build: Some("sbt -batch compile"),
+    doc: Some("sbt -batch doc"),
+    clean: Some("sbt -batch clean"),
+    run: Some("sbt -batch runMain"),
+    default_exec: Some("sbt -batch run"),
+    remove: "",
+    remove_global: "",
+    add: "",
+    add_global: "",
+    test: Some("sbt -batch test"),
+    search: "cs complete-dep",
+    upgrade: "cs update",
+    execute: Some("cs launch"),
+    new: Some("sbt new"),
+};
+
+/// Dependencies are `using dep` directives in project.scala
+pub const SCALA_CLI_MANAGER: Manager = Manager {
+    agent: "scala-cli",
+    build: Some("scala-cli compile ."),
+    doc: Some("scala-cli doc ."),
+    clean: Some("scala-cli clean ."),
+    run: Some("scala-cli run"),
+    default_exec: Some("scala-cli run ."),
+    remove: "",
+    remove_global: "",
+    add: "",
+    add_global: "",
+    test: Some("scala-cli test ."),
+    search: "cs complete-dep",
+    upgrade: "cs update",
+    execute: Some("cs launch"),
+    new: None,
+};
+
+pub const SWIFT_MANAGER: Manager = Manager {
+    agent: "swift",
+    build: Some("swift build"),
+    doc: None,
+    clean: Some("swift package clean"),
+    run: Some("swift run"),
+    default_exec: Some("swift run"),
+    remove: "swift package reset", // TODO: SwiftPM can't remove dependencies from manifest
+    remove_global: "swift package reset",
+    add: "swift package add-dependency",
+    add_global: "swift package add-dependency",
+    test: Some("swift test"),
+    search: "swift package show-dependencies",
+    upgrade: "swift package update",
+    execute: None,
+    new: Some("swift package init"),
+};
+
+/// Build and run commands depend on the detected [`crate::entities::build_system::BuildSystem`]
+pub const CLANGPP_MANAGER: Manager = Manager {
+    agent: "clang++",
+    build: None,
+    doc: None,
+    clean: None,
+    run: None,
+    default_exec: None,
+    remove: "vcpkg remove",
+    remove_global: "vcpkg remove",
+    add: "vcpkg add port",
+    add_global: "vcpkg install",
+    test: None,
+    search: "vcpkg search",
+    upgrade: "vcpkg update",
+    execute: None,
+    new: None,
+};
+
+/// Build and run commands depend on the detected [`crate::entities::build_system::BuildSystem`]
+pub const GCC_MANAGER: Manager = Manager {
+    agent: "gcc",
+    build: None,
+    doc: None,
+    clean: None,
+    run: None,
+    default_exec: None,
+    remove: "vcpkg remove",
+    remove_global: "vcpkg remove",
+    add: "vcpkg add port",
+    add_global: "vcpkg install",
+    test: None,
+    search: "vcpkg search",
+    upgrade: "vcpkg update",
+    execute: None,
+    new: None,
+};
+
+/// Build and run commands depend on the detected [`crate::entities::build_system::BuildSystem`]
+pub const CLANG_MANAGER: Manager = Manager {
+    agent: "clang",
+    build: None,
+    doc: None,
+    clean: None,
+    run: None,
+    default_exec: None,
+    remove: "vcpkg remove",
+    remove_global: "vcpkg remove",
+    add: "vcpkg add port",
+    add_global: "vcpkg install",
+    test: None,
+    search: "vcpkg search",
+    upgrade: "vcpkg update",
+    execute: None,
+    new: None,
+};
+
+/// Task runners only run targets, they don't manage dependencies
+pub const MAKE_MANAGER: Manager = Manager {
+    agent: "make",
+    build: Some("make build"),
+    doc: None,
+    clean: Some("make clean"),
+    run: Some("make"),
+    default_exec: Some("make"),
+    remove: "",
+    remove_global: "",
+    add: "",
+    add_global: "",
+    test: Some("make test"),
+    search: "",
+    upgrade: "",
+    execute: None,
+    new: None,
+};
+
+/// Task runners only run targets, they don't manage dependencies
+pub const JUST_MANAGER: Manager = Manager {
+    agent: "just",
+    build: Some("just build"),
+    doc: None,
+    clean: Some("just clean"),
+    run: Some("just"),
+    default_exec: Some("just"),
+    remove: "",
+    remove_global: "",
+    add: "",
+    add_global: "",
+    test: Some("just test"),
+    search: "",
+    upgrade: "",
+    execute: None,
+    new: None,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_args_test() {
+        let args = vec!["--port".to_owned(), "8080".to_owned()];
+
+        let mut p = vec!["run"];
+        CARGO_MANAGER.push_args("run", &mut p, &args);
+        assert_eq!(p, vec!["run", "--", "--port", "8080"]);
+
+        let mut p = vec!["build"];
+        CARGO_MANAGER.push_args("build", &mut p, &args);
+        assert_eq!(p, vec!["build", "--port", "8080"]);
+
+        let mut p = vec!["run", "dev"];
+        NPM_MANAGER.push_args("run", &mut p, &args);
+        assert_eq!(p, vec!["run", "dev", "--", "--port", "8080"]);
+
+        let mut p = vec!["run", "dev"];
+        NPM_MANAGER.push_args("run", &mut p, &[]);
+        assert_eq!(p, vec!["run", "dev"]);
+
+        let mut p = vec!["run", "dev"];
+        BUN_MANAGER.push_args("run", &mut p, &args);
+        assert_eq!(p, vec!["run", "dev", "--port", "8080"]);
+    }
+}
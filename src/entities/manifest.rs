@@ -1,11 +1,15 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fmt::Display;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use miette::IntoDiagnostic;
+use miette::{miette, IntoDiagnostic};
 use serde::{Deserialize, Serialize};
 
+use crate::parsers::rockspec::{rockspec_parser, LuaValue};
+
 #[derive(Serialize, Deserialize)]
 pub struct PackageJson {
     name: String,
@@ -55,6 +59,470 @@ impl TryFrom<PathBuf> for PackageJson {
     }
 }
 
+#[derive(Debug, Default, PartialEq)]
+pub struct Rockspec {
+    pub package: String,
+    pub version: String,
+    pub summary: String,
+    pub license: String,
+    pub dependencies: Vec<String>,
+    /// Modules from `build.modules` as (module name, path) pairs
+    pub modules: Vec<(String, String)>,
+    /// Scripts from `build.install.bin` as (command name, path) pairs
+    pub bin: Vec<(String, String)>,
+}
+
+impl Rockspec {
+    pub fn new(package: String, version: String, summary: String, license: String) -> Self {
+        Self {
+            package,
+            version,
+            summary,
+            license,
+            dependencies: vec!["lua >= 5.1".to_owned()],
+            modules: vec![],
+            bin: vec![],
+        }
+    }
+
+    /// Name of the rockspec file as expected by luarocks
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.rockspec", self.package, self.version)
+    }
+
+    /// Finds all `*.rockspec` files in given directory
+    pub fn find(dir: &Path) -> miette::Result<Vec<PathBuf>> {
+        let mut rockspecs = fs::read_dir(dir)
+            .into_diagnostic()?
+            .filter_map(Result::ok)
+            .map(|v| v.path())
+            .filter(|v| v.extension().is_some_and(|ext| ext == "rockspec"))
+            .collect::<Vec<_>>();
+        rockspecs.sort();
+        Ok(rockspecs)
+    }
+}
+
+impl FromStr for Rockspec {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, fields) =
+            rockspec_parser(s).map_err(|e| miette!("Failed to parse rockspec: {}", e))?;
+        let fields = LuaValue::Table(fields.into_iter().map(|(k, v)| (Some(k), v)).collect());
+
+        let string =
+            |v: Option<&LuaValue>| v.and_then(LuaValue::as_str).unwrap_or_default().to_owned();
+        let pairs = |v: Option<&LuaValue>| {
+            v.map(LuaValue::entries)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(k, v)| k.map(|k| (k, v)))
+                .collect::<Vec<_>>()
+        };
+
+        let build = fields.get("build");
+        Ok(Self {
+            package: string(fields.get("package")),
+            version: string(fields.get("version")),
+            summary: string(fields.get("description").and_then(|v| v.get("summary"))),
+            license: string(fields.get("description").and_then(|v| v.get("license"))),
+            dependencies: fields
+                .get("dependencies")
+                .map(LuaValue::entries)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect(),
+            modules: pairs(build.and_then(|v| v.get("modules"))),
+            bin: pairs(
+                build
+                    .and_then(|v| v.get("install"))
+                    .and_then(|v| v.get("bin")),
+            ),
+        })
+    }
+}
+
+impl TryFrom<PathBuf> for Rockspec {
+    type Error = miette::Error;
+
+    fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
+        let data = fs::read_to_string(value).into_diagnostic()?;
+        Self::from_str(&data)
+    }
+}
+
+impl Display for Rockspec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let table = |entries: &Vec<(String, String)>, indent: &str| {
+            entries
+                .iter()
+                .map(|(k, v)| format!("{indent}   [{:?}] = {:?},\n", k, v))
+                .collect::<String>()
+        };
+
+        writeln!(f, "rockspec_format = \"3.0\"")?;
+        writeln!(f, "package = {:?}", self.package)?;
+        writeln!(f, "version = {:?}", self.version)?;
+        writeln!(f, "source = {{")?;
+        writeln!(
+            f,
+            "   url = \"*** please add URL for source tarball, zip or repository here ***\","
+        )?;
+        writeln!(f, "}}")?;
+        writeln!(f, "description = {{")?;
+        writeln!(f, "   summary = {:?},", self.summary)?;
+        writeln!(f, "   license = {:?},", self.license)?;
+        writeln!(f, "}}")?;
+        writeln!(f, "dependencies = {{")?;
+        for dependency in &self.dependencies {
+            writeln!(f, "   {:?},", dependency)?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "build = {{")?;
+        writeln!(f, "   type = \"builtin\",")?;
+        writeln!(f, "   modules = {{\n{}   }},", table(&self.modules, "   "))?;
+        writeln!(f, "   install = {{")?;
+        writeln!(f, "      bin = {{\n{}      }},", table(&self.bin, "      "))?;
+        writeln!(f, "   }},")?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Output of `swift package dump-package`
+#[derive(Deserialize, Debug)]
+pub struct SwiftPackage {
+    pub name: String,
+    #[serde(default)]
+    pub products: Vec<SwiftProduct>,
+    #[serde(default)]
+    pub targets: Vec<SwiftTarget>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SwiftProduct {
+    pub name: String,
+    /// Product type is an object with single key like `executable` or `library`
+    #[serde(rename = "type")]
+    pub product_type: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SwiftTarget {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub target_type: String,
+}
+
+impl SwiftPackage {
+    /// Names of executables that can be passed to `swift run`
+    ///
+    /// Executable targets without explicit product get one implicitly
+    pub fn executables(&self) -> Vec<String> {
+        let mut executables = self
+            .products
+            .iter()
+            .filter(|v| v.product_type.get("executable").is_some())
+            .map(|v| v.name.clone())
+            .collect::<Vec<_>>();
+
+        for target in &self.targets {
+            if target.target_type == "executable" && !executables.contains(&target.name) {
+                executables.push(target.name.clone());
+            }
+        }
+
+        executables
+    }
+}
+
+impl FromStr for SwiftPackage {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s).into_diagnostic()
+    }
+}
+
+/// vcpkg manifest (`vcpkg.json`)
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VcpkgManifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Dependencies are either plain names or objects with `name` field
+    #[serde(default)]
+    pub dependencies: Vec<serde_json::Value>,
+    /// Fields ciri doesn't touch
+    #[serde(flatten)]
+    pub rest: serde_json::Map<String, serde_json::Value>,
+}
+
+impl VcpkgManifest {
+    pub fn new(name: &str) -> Self {
+        // Port names may only contain lowercase letters, digits and dashes
+        let name = name
+            .to_lowercase()
+            .chars()
+            .map(|v| if v.is_ascii_alphanumeric() { v } else { '-' })
+            .collect::<String>();
+        Self {
+            name: Some(name.trim_matches('-').to_owned()),
+            version: Some("0.1.0".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    pub fn dependency_names(&self) -> Vec<&str> {
+        self.dependencies
+            .iter()
+            .filter_map(|v| v.as_str().or_else(|| v.get("name")?.as_str()))
+            .collect()
+    }
+
+    /// Adds dependency, returns false if it was already present
+    pub fn add_dependency(&mut self, name: &str) -> bool {
+        if self.dependency_names().contains(&name) {
+            return false;
+        }
+
+        self.dependencies.push(serde_json::Value::from(name));
+        true
+    }
+
+    pub fn save(&self, path: &Path) -> miette::Result<()> {
+        let data = serde_json::to_string_pretty(self).into_diagnostic()?;
+        fs::write(path, data + "\n").into_diagnostic()
+    }
+}
+
+impl TryFrom<PathBuf> for VcpkgManifest {
+    type Error = miette::Error;
+
+    fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
+        let data = fs::read_to_string(value).into_diagnostic()?;
+        serde_json::from_str(&data).into_diagnostic()
+    }
+}
+
+/// Conan dependencies file (`conanfile.txt`)
+///
+/// Kept as lines so that comments and unknown sections survive editing
+#[derive(Debug, Default, PartialEq)]
+pub struct ConanFile {
+    lines: Vec<String>,
+}
+
+impl ConanFile {
+    /// References listed in `[requires]` section
+    pub fn requires(&self) -> Vec<&str> {
+        self.section("requires")
+            .map(|(start, end)| {
+                self.lines[start..end]
+                    .iter()
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty() && !v.starts_with('#'))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Adds reference like `fmt/10.2.1`, replacing other version of the same package
+    pub fn add_require(&mut self, reference: &str) {
+        let name = reference.split('/').next().unwrap_or(reference);
+        let Some((start, end)) = self.section("requires") else {
+            if self.lines.last().is_some_and(|v| !v.trim().is_empty()) {
+                self.lines.push(String::new());
+            }
+            self.lines.push("[requires]".to_owned());
+            self.lines.push(reference.to_owned());
+            return;
+        };
+
+        if let Some(line) = self.lines[start..end]
+            .iter_mut()
+            .find(|v| v.trim().split('/').next() == Some(name))
+        {
+            *line = reference.to_owned();
+            return;
+        }
+
+        // Insert after last entry so blank line before next section is kept
+        let last = self.lines[start..end]
+            .iter()
+            .rposition(|v| !v.trim().is_empty())
+            .map(|v| start + v + 1)
+            .unwrap_or(start);
+        self.lines.insert(last, reference.to_owned());
+    }
+
+    /// Range of lines belonging to a section, without its header
+    fn section(&self, name: &str) -> Option<(usize, usize)> {
+        let header = format!("[{}]", name);
+        let start = self.lines.iter().position(|v| v.trim() == header)? + 1;
+        let end = self.lines[start..]
+            .iter()
+            .position(|v| v.trim().starts_with('['))
+            .map(|v| start + v)
+            .unwrap_or(self.lines.len());
+        Some((start, end))
+    }
+}
+
+impl FromStr for ConanFile {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            lines: s.lines().map(ToOwned::to_owned).collect(),
+        })
+    }
+}
+
+impl TryFrom<PathBuf> for ConanFile {
+    type Error = miette::Error;
+
+    fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
+        fs::read_to_string(value).into_diagnostic()?.parse()
+    }
+}
+
+impl Display for ConanFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scala dependency like `org.typelevel::cats-core:2.10.0`, `::` marks a library published
+/// for each Scala version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalaDependency {
+    pub organization: String,
+    pub name: String,
+    pub version: String,
+    pub cross_version: bool,
+}
+
+impl ScalaDependency {
+    fn separator(&self) -> &'static str {
+        if self.cross_version {
+            "::"
+        } else {
+            ":"
+        }
+    }
+
+    /// Setting of build.sbt, `%%` appends Scala version to the name like `::` does
+    pub fn sbt_setting(&self) -> String {
+        format!(
+            "libraryDependencies += \"{}\" {} \"{}\" % \"{}\"",
+            self.organization,
+            if self.cross_version { "%%" } else { "%" },
+            self.name,
+            self.version
+        )
+    }
+
+    /// Directive of scala-cli sources
+    pub fn using_directive(&self) -> String {
+        format!("//> using dep {}", self)
+    }
+
+    /// Adds setting to build.sbt, replacing other version of the same library
+    pub fn add_to_sbt(&self, build: &str) -> String {
+        let library = format!("\"{}\"", self.name);
+        let organization = format!("\"{}\"", self.organization);
+        let mut lines: Vec<String> = build.lines().map(ToOwned::to_owned).collect();
+        match lines.iter_mut().find(|v| {
+            v.trim_start().starts_with("libraryDependencies +=")
+                && v.contains(&organization)
+                && v.contains(&library)
+        }) {
+            Some(line) => *line = self.sbt_setting(),
+            None => lines.push(self.sbt_setting()),
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Adds directive after the ones at the start of scala-cli source, directives after code
+    /// are ignored. Other version of the same library is replaced
+    pub fn add_to_scala_cli(&self, source: &str) -> String {
+        let prefix = format!("{}{}{}:", self.organization, self.separator(), self.name);
+        let mut lines: Vec<String> = source.lines().map(ToOwned::to_owned).collect();
+        if let Some(line) = lines.iter_mut().find(|v| {
+            v.trim()
+                .strip_prefix("//> using dep ")
+                .is_some_and(|v| v.trim().trim_matches('"').starts_with(&prefix))
+        }) {
+            *line = self.using_directive();
+        } else {
+            let header = lines
+                .iter()
+                .take_while(|v| v.trim().is_empty() || v.trim_start().starts_with("//"))
+                .count();
+            let last = lines[..header]
+                .iter()
+                .rposition(|v| v.trim_start().starts_with("//>"))
+                .map_or(0, |v| v + 1);
+            lines.insert(last, self.using_directive());
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+impl FromStr for ScalaDependency {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (organization, rest, cross_version) = match s.split_once("::") {
+            Some((organization, rest)) => (organization, rest, true),
+            None => {
+                let (organization, rest) = s.split_once(':').ok_or(miette!(
+                    "\"{}\" isn't a Scala dependency, use \"<organization>::<name>:<version>\"",
+                    s
+                ))?;
+                (organization, rest, false)
+            }
+        };
+        let (name, version) = rest.split_once(':').ok_or(miette!(
+            "Version of \"{}\" is missing, use \"{}:<version>\"",
+            s,
+            s
+        ))?;
+        if [organization, name, version]
+            .iter()
+            .any(|v| v.is_empty() || v.contains(':'))
+        {
+            return Err(miette!("\"{}\" isn't a valid Scala dependency", s));
+        }
+
+        Ok(Self {
+            organization: organization.to_owned(),
+            name: name.to_owned(),
+            version: version.to_owned(),
+            cross_version,
+        })
+    }
+}
+
+impl Display for ScalaDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{}:{}",
+            self.organization,
+            self.separator(),
+            self.name,
+            self.version
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,4 +557,130 @@ mod tests {
         assert_eq!(package_json.author, pkg_author);
         assert_eq!(package_json.license, pkg_license);
     }
+
+    #[test]
+    fn rockspec_round_trip_test() {
+        let mut rockspec = Rockspec::new(
+            "ciri".to_owned(),
+            "1.0.0-1".to_owned(),
+            "A \"test\" package".to_owned(),
+            "MIT".to_owned(),
+        );
+        rockspec.modules = vec![("ciri".to_owned(), "src/ciri.lua".to_owned())];
+        rockspec.bin = vec![("ciri".to_owned(), "src/main.lua".to_owned())];
+
+        assert_eq!(rockspec.file_name(), "ciri-1.0.0-1.rockspec");
+        assert_eq!(Rockspec::from_str(&rockspec.to_string()).unwrap(), rockspec);
+    }
+
+    #[test]
+    fn rockspec_without_build_test() {
+        let rockspec = Rockspec::from_str("package = 'ciri'\nversion = '0.1-1'").unwrap();
+        assert_eq!(rockspec.package, "ciri");
+        assert_eq!(rockspec.version, "0.1-1");
+        assert!(rockspec.bin.is_empty());
+
+        assert!(Rockspec::from_str("local x = 1").is_err());
+    }
+
+    #[test]
+    fn swift_package_executables_test() {
+        let package = SwiftPackage::from_str(
+            r#"{
+              "name": "example",
+              "products": [
+                { "name": "cli", "targets": ["cli"], "type": { "executable": null } },
+                { "name": "Core", "targets": ["Core"], "type": { "library": ["automatic"] } }
+              ],
+              "targets": [
+                { "name": "cli", "type": "executable" },
+                { "name": "server", "type": "executable" },
+                { "name": "Core", "type": "regular" },
+                { "name": "CoreTests", "type": "test" }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(package.name, "example");
+        assert_eq!(package.executables(), vec!["cli", "server"]);
+        assert!(SwiftPackage::from_str("error: no Package.swift").is_err());
+    }
+
+    #[test]
+    fn vcpkg_manifest_test() {
+        let mut manifest: VcpkgManifest = serde_json::from_str(
+            r#"{ "dependencies": ["fmt", { "name": "boost-asio", "features": [] }], "builtin-baseline": "abc" }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.dependency_names(), vec!["fmt", "boost-asio"]);
+        assert!(!manifest.add_dependency("fmt"));
+        assert!(manifest.add_dependency("spdlog"));
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["dependencies"][2], "spdlog");
+        assert_eq!(json["builtin-baseline"], "abc");
+        assert!(json.get("name").is_none());
+
+        assert_eq!(VcpkgManifest::new("My_App").name, Some("my-app".to_owned()));
+    }
+
+    #[test]
+    fn conanfile_test() {
+        let mut conanfile =
+            ConanFile::from_str("[requires]\nfmt/9.0.0\n\n[generators]\nCMakeDeps\n").unwrap();
+        conanfile.add_require("fmt/10.2.1");
+        conanfile.add_require("zlib/1.3");
+        assert_eq!(conanfile.requires(), vec!["fmt/10.2.1", "zlib/1.3"]);
+        assert_eq!(
+            conanfile.to_string(),
+            "[requires]\nfmt/10.2.1\nzlib/1.3\n\n[generators]\nCMakeDeps\n"
+        );
+
+        let mut conanfile = ConanFile::from_str("[generators]\nCMakeDeps").unwrap();
+        conanfile.add_require("zlib/1.3");
+        assert_eq!(
+            conanfile.to_string(),
+            "[generators]\nCMakeDeps\n\n[requires]\nzlib/1.3\n"
+        );
+    }
+
+    #[test]
+    fn scala_dependency_test() -> miette::Result<()> {
+        let cats = ScalaDependency::from_str("org.typelevel::cats-core:2.10.0")?;
+        assert_eq!(
+            cats.sbt_setting(),
+            "libraryDependencies += \"org.typelevel\" %% \"cats-core\" % \"2.10.0\""
+        );
+        let junit = ScalaDependency::from_str("junit:junit:4.13.2")?;
+        assert_eq!(junit.using_directive(), "//> using dep junit:junit:4.13.2");
+        assert!(ScalaDependency::from_str("cats-core").is_err());
+        assert!(ScalaDependency::from_str("org.typelevel::cats-core").is_err());
+        assert!(ScalaDependency::from_str("a:b:c:d").is_err());
+
+        let build = "name := \"app\"\nlibraryDependencies += \"org.typelevel\" %% \"cats-core\" % \"2.9.0\"";
+        assert_eq!(
+            cats.add_to_sbt(build),
+            format!("name := \"app\"\n{}\n", cats.sbt_setting())
+        );
+        assert_eq!(
+            junit.add_to_sbt("name := \"app\"\n"),
+            format!("name := \"app\"\n{}\n", junit.sbt_setting())
+        );
+
+        let source = "// Project\n//> using scala 3.3.1\n\nobject Main";
+        assert_eq!(
+            cats.add_to_scala_cli(source),
+            "// Project\n//> using scala 3.3.1\n//> using dep org.typelevel::cats-core:2.10.0\n\nobject Main\n"
+        );
+        assert_eq!(
+            cats.add_to_scala_cli("//> using dep \"org.typelevel::cats-core:2.9.0\"\n"),
+            "//> using dep org.typelevel::cats-core:2.10.0\n"
+        );
+        assert_eq!(
+            junit.add_to_scala_cli("object Main"),
+            "//> using dep junit:junit:4.13.2\nobject Main\n"
+        );
+        Ok(())
+    }
 }
@@ -1,2 +1,15 @@
+pub mod build_system;
+pub mod cmake_file_api;
+pub mod env;
+pub mod export;
+pub mod fingerprint;
+pub mod history;
+pub mod limits;
 pub mod managers;
 pub mod manifest;
+pub mod processes;
+pub mod profile;
+pub mod summary;
+pub mod task_runner;
+pub mod tasks;
+pub mod test_report;
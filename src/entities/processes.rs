@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{bail, miette, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+use crate::Config;
+
+/// Long running process started by `ciri start`, defined in `[processes]` table of `.ciri.toml`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Process {
+    /// Shell command to execute
+    pub command: String,
+    /// Directory to run command in, relative to project root
+    pub cwd: Option<PathBuf>,
+    pub env: Option<HashMap<String, String>>,
+    /// Start process again when it exits
+    pub restart_on_exit: Option<bool>,
+}
+
+/// Parses Procfile where every line is `name: command`
+pub fn parse_procfile(input: &str) -> miette::Result<Vec<(String, Process)>> {
+    let mut processes: Vec<(String, Process)> = vec![];
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let valid = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        };
+        match line.split_once(':') {
+            Some((name, command)) if valid(name) && !command.trim().is_empty() => {
+                if processes.iter().any(|(v, _)| v == name) {
+                    bail!("Process \"{}\" is defined twice in Procfile", name);
+                }
+                processes.push((
+                    name.to_owned(),
+                    Process {
+                        command: command.trim().to_owned(),
+                        ..Default::default()
+                    },
+                ));
+            }
+            _ => bail!("Invalid Procfile entry on line {}: {}", i + 1, line),
+        }
+    }
+
+    Ok(processes)
+}
+
+/// Processes from `[processes]` table of config, or Procfile in `dir` when there is none
+pub fn load_processes(dir: &Path, config: &Config) -> miette::Result<Vec<(String, Process)>> {
+    if let Some(processes) = &config.processes {
+        let mut processes = processes
+            .iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect::<Vec<_>>();
+        processes.sort_by(|a, b| a.0.cmp(&b.0));
+        return Ok(processes);
+    }
+
+    let procfile = dir.join("Procfile");
+    if !procfile.exists() {
+        return Err(miette!(
+            help = "Create Procfile or add [processes] table to .ciri.toml",
+            "No processes were defined"
+        ));
+    }
+    parse_procfile(&fs::read_to_string(procfile).into_diagnostic()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_procfile_test() {
+        let processes = parse_procfile(
+            "# comment
+web: npm run dev -- --port $PORT
+
+worker:   python worker.py
+",
+        )
+        .unwrap();
+        assert_eq!(
+            processes
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.command.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("web", "npm run dev -- --port $PORT"),
+                ("worker", "python worker.py")
+            ]
+        );
+
+        let err = parse_procfile("web npm start").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid Procfile entry on line 1: web npm start"
+        );
+        assert!(parse_procfile("web: a\nweb: b").is_err());
+    }
+
+    #[test]
+    fn parse_processes_test() {
+        let config: Config = toml::from_str(
+            r#"
+            [processes.web]
+            command = "npm run dev"
+            restart_on_exit = true
+
+            [processes.api]
+            command = "cargo run"
+            cwd = "api"
+            "#,
+        )
+        .unwrap();
+
+        let processes = load_processes(Path::new("/nonexistent"), &config).unwrap();
+        assert_eq!(processes[0].0, "api");
+        assert_eq!(processes[0].1.cwd, Some(PathBuf::from("api")));
+        assert_eq!(processes[1].1.restart_on_exit, Some(true));
+        assert!(load_processes(Path::new("/nonexistent"), &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use duct::Expression;
+use miette::bail;
+use serde::{Deserialize, Serialize};
+
+use super::build_system::BuildType;
+use crate::Config;
+
+/// Custom profile defined in `[profiles]` table of `.ciri.toml`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Built-in or custom profile this one extends
+    pub inherits: Option<String>,
+    /// Arguments passed to the underlying tool
+    pub flags: Option<Vec<String>>,
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// What selected profile means for a specific manager
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedProfile {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Build type of C/C++ projects
+    pub build_type: Option<BuildType>,
+}
+
+impl ResolvedProfile {
+    /// Resolves profile selected by flags against profiles from `.ciri.toml`
+    pub fn load(
+        agent: &str,
+        release: bool,
+        debug: bool,
+        profile: Option<&str>,
+    ) -> miette::Result<Self> {
+        let profiles = Config::load()?.profiles.unwrap_or_default();
+        Self::resolve(agent, release, debug, profile, &profiles)
+    }
+
+    /// `--release` and `--debug` are the same as `--profile release` and `--profile debug`,
+    /// so they can be customized in config too
+    pub fn resolve(
+        agent: &str,
+        release: bool,
+        debug: bool,
+        profile: Option<&str>,
+        profiles: &HashMap<String, Profile>,
+    ) -> miette::Result<Self> {
+        let name = match (profile, release, debug) {
+            (Some(name), _, _) => name,
+            (None, true, _) => "release",
+            (None, _, true) => "debug",
+            (None, false, false) => return Ok(Self::default()),
+        };
+
+        resolve_custom(agent, name, profiles, &mut vec![])
+    }
+
+    /// Sets profile's environment variables on expression
+    pub fn apply(&self, mut expression: Expression) -> Expression {
+        for (key, value) in &self.env {
+            expression = expression.env(key, value);
+        }
+        expression
+    }
+
+    fn extend(&mut self, profile: &Profile) {
+        if let Some(flags) = &profile.flags {
+            self.args.extend(flags.iter().cloned());
+        }
+        if let Some(env) = &profile.env {
+            let mut env = env.iter().collect::<Vec<_>>();
+            env.sort();
+            for (key, value) in env {
+                self.env.retain(|(k, _)| k != key);
+                self.env.push((key.to_owned(), value.to_owned()));
+            }
+        }
+    }
+}
+
+fn resolve_custom(
+    agent: &str,
+    name: &str,
+    profiles: &HashMap<String, Profile>,
+    path: &mut Vec<String>,
+) -> miette::Result<ResolvedProfile> {
+    let Some(profile) = profiles.get(name) else {
+        return match builtin(agent, name) {
+            Some(resolved) => Ok(resolved),
+            None => bail!(
+                help = "Define it in [profiles] table of .ciri.toml",
+                "Profile \"{}\" isn't supported by {}",
+                name,
+                agent
+            ),
+        };
+    };
+
+    if path.iter().any(|v| v == name) {
+        path.push(name.to_owned());
+        bail!("Profiles inherit from each other: {}", path.join(" -> "));
+    }
+    path.push(name.to_owned());
+
+    // Custom profile named like a built-in one extends it
+    let mut resolved = match profile.inherits.as_deref() {
+        Some(parent) if parent == name => builtin(agent, name).unwrap_or_default(),
+        Some(parent) => resolve_custom(agent, parent, profiles, path)?,
+        None => builtin(agent, name).unwrap_or_default(),
+    };
+    resolved.extend(profile);
+
+    path.pop();
+    Ok(resolved)
+}
+
+/// Built-in meaning of profile for manager, `None` if manager doesn't know it
+fn builtin(agent: &str, name: &str) -> Option<ResolvedProfile> {
+    let args = |args: &[&str]| ResolvedProfile {
+        args: args.iter().map(|v| v.to_string()).collect(),
+        ..Default::default()
+    };
+
+    match agent {
+        "cargo" => match name {
+            "release" => Some(args(&["--release"])),
+            "debug" | "dev" => Some(ResolvedProfile::default()),
+            // Cargo supports custom profiles in Cargo.toml
+            _ => Some(args(&["--profile", name])),
+        },
+        "npm" | "pnpm" | "yarn" | "bun" => {
+            let node_env = match name {
+                "release" => "production",
+                "debug" => "development",
+                _ => name,
+            };
+            Some(ResolvedProfile {
+                env: vec![("NODE_ENV".to_owned(), node_env.to_owned())],
+                ..Default::default()
+            })
+        }
+        "go" => match name {
+            "release" => Some(args(&["-ldflags=-s -w"])),
+            "debug" => Some(args(&["-gcflags=all=-N -l"])),
+            _ => None,
+        },
+        "swift" => match name {
+            "release" | "debug" => Some(args(&["-c", name])),
+            _ => None,
+        },
+        "gcc" | "g++" | "clang" | "clang++" => {
+            BuildType::from_name(name).map(|build_type| ResolvedProfile {
+                build_type: Some(build_type),
+                ..Default::default()
+            })
+        }
+        _ => match name {
+            "release" | "debug" => Some(ResolvedProfile::default()),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles_test() {
+        let profiles = HashMap::new();
+        let resolve = |agent, release, debug, profile| {
+            ResolvedProfile::resolve(agent, release, debug, profile, &profiles).unwrap()
+        };
+
+        assert_eq!(
+            resolve("cargo", false, false, None),
+            ResolvedProfile::default()
+        );
+        assert_eq!(resolve("cargo", true, false, None).args, ["--release"]);
+        assert_eq!(
+            resolve("cargo", false, false, Some("bench")).args,
+            ["--profile", "bench"]
+        );
+        assert_eq!(
+            resolve("npm", true, false, None).env,
+            [("NODE_ENV".to_owned(), "production".to_owned())]
+        );
+        assert_eq!(resolve("go", true, false, None).args, ["-ldflags=-s -w"]);
+        assert_eq!(resolve("swift", false, true, None).args, ["-c", "debug"]);
+        assert_eq!(
+            resolve("g++", false, false, Some("RelWithDebInfo")).build_type,
+            Some(BuildType::RelWithDebInfo)
+        );
+
+        let err = ResolvedProfile::resolve("g++", false, false, Some("fast"), &profiles);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Profile \"fast\" isn't supported by g++"
+        );
+    }
+
+    #[test]
+    fn custom_profiles_test() {
+        let profiles: HashMap<String, Profile> = toml::from_str(
+            r#"
+            [release]
+            env = { RUST_LOG = "warn" }
+
+            [profiling]
+            inherits = "release"
+            flags = ["--features", "profiling"]
+            env = { RUST_LOG = "info" }
+
+            [a]
+            inherits = "b"
+
+            [b]
+            inherits = "a"
+            "#,
+        )
+        .unwrap();
+
+        let release = ResolvedProfile::resolve("cargo", true, false, None, &profiles).unwrap();
+        assert_eq!(release.args, ["--release"]);
+        assert_eq!(release.env, [("RUST_LOG".to_owned(), "warn".to_owned())]);
+
+        let profiling =
+            ResolvedProfile::resolve("cargo", false, false, Some("profiling"), &profiles).unwrap();
+        assert_eq!(profiling.args, ["--release", "--features", "profiling"]);
+        assert_eq!(profiling.env, [("RUST_LOG".to_owned(), "info".to_owned())]);
+
+        let err = ResolvedProfile::resolve("cargo", false, false, Some("a"), &profiles);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Profiles inherit from each other: a -> b -> a"
+        );
+    }
+}
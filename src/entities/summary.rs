@@ -0,0 +1,135 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::limits::format_size;
+
+/// Resources used by finished child processes of ciri
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChildUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// Largest resident set of a single child, in bytes
+    pub peak_rss: u64,
+}
+
+impl ChildUsage {
+    /// Usage of every child waited for so far
+    pub fn now() -> Self {
+        // SAFETY: rusage is plain data and getrusage fills it in
+        let usage = unsafe {
+            let mut usage = std::mem::zeroed::<libc::rusage>();
+            libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage);
+            usage
+        };
+        let time = |v: libc::timeval| {
+            Duration::from_secs(v.tv_sec as u64) + Duration::from_micros(v.tv_usec as u64)
+        };
+
+        Self {
+            user_time: time(usage.ru_utime),
+            system_time: time(usage.ru_stime),
+            // Linux reports it in kilobytes
+            peak_rss: usage.ru_maxrss as u64 * 1024,
+        }
+    }
+}
+
+/// What a subcommand took, printed after build, run and test
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub command: String,
+    /// Seconds
+    pub wall_time: f64,
+    /// Seconds of CPU time spent in child processes
+    pub user_time: f64,
+    pub system_time: f64,
+    /// Bytes
+    pub peak_rss: u64,
+    pub success: bool,
+    pub exit_code: i32,
+}
+
+impl Summary {
+    /// Summary of usage between `before` and `after`
+    ///
+    /// Peak RSS can't be measured for a period, so it is the peak of any child so far
+    pub fn new(
+        command: &str,
+        wall_time: Duration,
+        before: ChildUsage,
+        after: ChildUsage,
+        exit_code: i32,
+    ) -> Self {
+        Self {
+            command: command.to_owned(),
+            wall_time: wall_time.as_secs_f64(),
+            user_time: after
+                .user_time
+                .saturating_sub(before.user_time)
+                .as_secs_f64(),
+            system_time: after
+                .system_time
+                .saturating_sub(before.system_time)
+                .as_secs_f64(),
+            peak_rss: after.peak_rss,
+            success: exit_code == 0,
+            exit_code,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} in {:.2}s (user {:.2}s, sys {:.2}s, peak RSS {}), exit status {}",
+            self.command,
+            if self.success { "finished" } else { "failed" },
+            self.wall_time,
+            self.user_time,
+            self.system_time,
+            format_size(self.peak_rss),
+            self.exit_code
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_test() {
+        let before = ChildUsage {
+            user_time: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let after = ChildUsage {
+            user_time: Duration::from_millis(1750),
+            system_time: Duration::from_millis(250),
+            peak_rss: 3 * 1024 * 1024,
+        };
+
+        let summary = Summary::new("build", Duration::from_millis(2500), before, after, 0);
+        assert_eq!(
+            summary.to_string(),
+            "build finished in 2.50s (user 1.25s, sys 0.25s, peak RSS 3.0 MiB), exit status 0"
+        );
+        assert_eq!(
+            serde_json::to_string(&Summary::new("test", Duration::ZERO, after, after, 101))
+                .unwrap(),
+            r#"{"command":"test","wall_time":0.0,"user_time":0.0,"system_time":0.0,"peak_rss":3145728,"success":false,"exit_code":101}"#
+        );
+    }
+
+    #[test]
+    fn child_usage_test() {
+        let before = ChildUsage::now();
+        std::process::Command::new("true").status().unwrap();
+        let after = ChildUsage::now();
+        assert!(after.peak_rss > 0);
+        assert!(after.user_time >= before.user_time);
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use crate::PackageManagers;
+
+/// Tools that only run named targets, used when no language manager was detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskRunner {
+    Just,
+    Make,
+}
+
+impl TaskRunner {
+    const FILES: [(&'static str, Self); 6] = [
+        ("justfile", Self::Just),
+        ("Justfile", Self::Just),
+        (".justfile", Self::Just),
+        ("GNUmakefile", Self::Make),
+        ("Makefile", Self::Make),
+        ("makefile", Self::Make),
+    ];
+
+    /// Every task runner that has its file in directory
+    pub fn detect(dir: &Path) -> Vec<Self> {
+        let mut runners = Self::FILES
+            .iter()
+            .filter(|(file, _)| dir.join(file).exists())
+            .map(|(_, runner)| *runner)
+            .collect::<Vec<_>>();
+        runners.dedup();
+        runners
+    }
+
+    pub fn from_manager(manager: &PackageManagers) -> Option<Self> {
+        match manager {
+            PackageManagers::Just => Some(Self::Just),
+            PackageManagers::Make => Some(Self::Make),
+            _ => None,
+        }
+    }
+
+    pub fn file(&self, dir: &Path) -> Option<&'static str> {
+        Self::FILES
+            .iter()
+            .find(|(file, runner)| runner == self && dir.join(file).exists())
+            .map(|(file, _)| *file)
+    }
+
+    /// Targets used in place of ciri subcommands, in order of preference
+    pub fn conventional_targets(subcommand: &str) -> &'static [&'static str] {
+        match subcommand {
+            "build" => &["build", "all"],
+            "test" => &["test", "check"],
+            "clean" => &["clean"],
+            _ => &[],
+        }
+    }
+
+    /// First of conventional targets for subcommand that project defines
+    pub fn target_for<'a>(subcommand: &str, targets: &'a [String]) -> Option<&'a str> {
+        Self::conventional_targets(subcommand)
+            .iter()
+            .find_map(|v| targets.iter().find(|target| target == v))
+            .map(String::as_str)
+    }
+}
+
+/// Extracts explicit targets from a Makefile
+///
+/// Special targets (`.PHONY`), pattern rules and variable assignments are skipped
+pub fn parse_makefile_targets(input: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+    for line in input.lines() {
+        // Recipes start with a tab
+        if line.starts_with('\t') || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+            continue;
+        }
+
+        for name in names.split_whitespace() {
+            let valid = !name.starts_with('.')
+                && !name.contains('%')
+                && !name.contains('$')
+                && !targets.iter().any(|v| v == name);
+            if valid {
+                targets.push(name.to_owned());
+            }
+        }
+    }
+
+    targets
+}
+
+/// Parses output of `just --summary`, which is a single line of recipe names
+pub fn parse_just_summary(input: &str) -> Vec<String> {
+    input.split_whitespace().map(ToOwned::to_owned).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_makefile_targets_test() {
+        let input = "CC := gcc
+CFLAGS = -O2
+.PHONY: all build test
+# comment: not a target
+all: build
+
+build test: main.o
+\t$(CC) main.o -o app
+
+%.o: %.c
+\t$(CC) -c $<
+
+clean::
+\trm -f app *.o
+";
+        assert_eq!(
+            parse_makefile_targets(input),
+            vec!["all", "build", "test", "clean"]
+        );
+    }
+
+    #[test]
+    fn target_for_test() {
+        let targets = vec!["all".to_owned(), "check".to_owned()];
+        assert_eq!(TaskRunner::target_for("build", &targets), Some("all"));
+        assert_eq!(TaskRunner::target_for("test", &targets), Some("check"));
+        assert_eq!(TaskRunner::target_for("clean", &targets), None);
+
+        assert_eq!(
+            parse_just_summary("build lint test\n"),
+            vec!["build", "lint", "test"]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use miette::bail;
+use serde::{Deserialize, Serialize};
+
+/// Task defined in `[tasks]` table of `.ciri.toml`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Task {
+    /// Shell command to execute
+    pub command: String,
+    /// Directory to run command in, relative to project root
+    pub cwd: Option<PathBuf>,
+    pub env: Option<HashMap<String, String>>,
+    /// Tasks that have to finish successfully before this one starts
+    pub depends_on: Option<Vec<String>>,
+    pub description: Option<String>,
+}
+
+impl Task {
+    pub fn dependencies(&self) -> &[String] {
+        self.depends_on.as_deref().unwrap_or_default()
+    }
+}
+
+/// Single hook, either shell command or reference to a task
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Hook {
+    Command(String),
+    Task { task: String },
+}
+
+/// Commands run around subcommands, defined in `[hooks]` table of `.ciri.toml`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Hooks {
+    pub pre_build: Option<Vec<Hook>>,
+    pub post_build: Option<Vec<Hook>>,
+    pub pre_run: Option<Vec<Hook>>,
+    pub post_run: Option<Vec<Hook>>,
+    pub pre_test: Option<Vec<Hook>>,
+    pub post_test: Option<Vec<Hook>>,
+    pub pre_clean: Option<Vec<Hook>>,
+    pub post_clean: Option<Vec<Hook>>,
+}
+
+impl Hooks {
+    /// Hooks by their name, e.g. `pre_build`
+    pub fn get(&self, name: &str) -> &[Hook] {
+        let hooks = match name {
+            "pre_build" => &self.pre_build,
+            "post_build" => &self.post_build,
+            "pre_run" => &self.pre_run,
+            "post_run" => &self.post_run,
+            "pre_test" => &self.pre_test,
+            "post_test" => &self.post_test,
+            "pre_clean" => &self.pre_clean,
+            "post_clean" => &self.post_clean,
+            _ => &None,
+        };
+        hooks.as_deref().unwrap_or_default()
+    }
+}
+
+/// Returns task and all its dependencies, every task after the ones it depends on
+pub fn resolve(tasks: &HashMap<String, Task>, name: &str) -> miette::Result<Vec<String>> {
+    fn visit(
+        tasks: &HashMap<String, Task>,
+        name: &str,
+        path: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) -> miette::Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if path.iter().any(|v| v == name) {
+            path.push(name.to_owned());
+            bail!("Tasks depend on each other: {}", path.join(" -> "));
+        }
+
+        let Some(task) = tasks.get(name) else {
+            match path.last() {
+                Some(parent) => bail!("Task \"{}\" depends on unknown task \"{}\"", parent, name),
+                None => bail!("Task \"{}\" isn't defined in .ciri.toml", name),
+            }
+        };
+
+        path.push(name.to_owned());
+        for dependency in task.dependencies() {
+            visit(tasks, dependency, path, done, order)?;
+        }
+        path.pop();
+
+        done.insert(name.to_owned());
+        order.push(name.to_owned());
+        Ok(())
+    }
+
+    let mut order = vec![];
+    visit(tasks, name, &mut vec![], &mut HashSet::new(), &mut order)?;
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(depends_on: &[&str]) -> Task {
+        Task {
+            command: "true".to_owned(),
+            depends_on: Some(depends_on.iter().map(|v| v.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolve_test() {
+        let tasks = HashMap::from([
+            ("build".to_owned(), task(&["codegen", "assets"])),
+            ("codegen".to_owned(), task(&[])),
+            ("assets".to_owned(), task(&["codegen"])),
+            ("lint".to_owned(), task(&[])),
+        ]);
+
+        assert_eq!(
+            resolve(&tasks, "build").unwrap(),
+            vec!["codegen", "assets", "build"]
+        );
+        assert_eq!(resolve(&tasks, "lint").unwrap(), vec!["lint"]);
+        assert!(resolve(&tasks, "missing").is_err());
+    }
+
+    #[test]
+    fn resolve_errors_test() {
+        let tasks = HashMap::from([
+            ("a".to_owned(), task(&["b"])),
+            ("b".to_owned(), task(&["a"])),
+            ("c".to_owned(), task(&["missing"])),
+        ]);
+
+        let err = resolve(&tasks, "a").unwrap_err();
+        assert_eq!(err.to_string(), "Tasks depend on each other: a -> b -> a");
+
+        let err = resolve(&tasks, "c").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Task \"c\" depends on unknown task \"missing\""
+        );
+    }
+
+    #[test]
+    fn parse_tasks_test() {
+        let tasks: HashMap<String, Task> = toml::from_str(
+            r#"
+            [build]
+            command = "cargo build"
+            depends_on = ["codegen"]
+            description = "Build everything"
+
+            [codegen]
+            command = "./gen.sh"
+            cwd = "scripts"
+            env = { OUT = "gen" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(tasks["build"].dependencies(), ["codegen"]);
+        assert_eq!(tasks["codegen"].cwd, Some(PathBuf::from("scripts")));
+        assert_eq!(
+            tasks["codegen"].env.as_ref().unwrap().get("OUT").unwrap(),
+            "gen"
+        );
+    }
+
+    #[test]
+    fn parse_hooks_test() {
+        let hooks: Hooks = toml::from_str(
+            r#"
+            pre_build = ["./codegen.sh", { task = "migrate" }]
+            post_test = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            hooks.get("pre_build"),
+            [
+                Hook::Command("./codegen.sh".to_owned()),
+                Hook::Task {
+                    task: "migrate".to_owned()
+                }
+            ]
+        );
+        assert!(hooks.get("post_test").is_empty());
+        assert!(hooks.get("pre_run").is_empty());
+        assert!(hooks.get("unknown").is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::IntoDiagnostic;
+use serde::{Deserialize, Serialize};
+
+use super::fingerprint::project_key;
+use crate::state_dir;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Single test, same for every test runner
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    /// Test binary, file or class that test belongs to
+    pub suite: Option<String>,
+    pub status: TestStatus,
+    /// Seconds
+    pub duration: Option<f64>,
+    /// Failure message and output captured by test runner
+    pub output: Option<String>,
+    /// Failed at first, but passed when it was retried
+    #[serde(default)]
+    pub flaky: bool,
+}
+
+impl TestCase {
+    pub fn new(name: &str, status: TestStatus) -> Self {
+        Self {
+            name: name.to_owned(),
+            suite: None,
+            status,
+            duration: None,
+            output: None,
+            flaky: false,
+        }
+    }
+
+    /// Name with suite, e.g. `src/lib.rs::tests::parse`
+    pub fn full_name(&self) -> String {
+        match &self.suite {
+            Some(suite) => format!("{}::{}", suite, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Results of tests parsed from output of libtest, TAP, ctest or JUnit XML
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TestReport {
+    pub cases: Vec<TestCase>,
+}
+
+impl TestReport {
+    pub fn count(&self, status: TestStatus) -> usize {
+        self.cases.iter().filter(|v| v.status == status).count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &TestCase> {
+        self.cases.iter().filter(|v| v.status == TestStatus::Failed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cases.is_empty()
+    }
+
+    pub fn extend(&mut self, other: TestReport) {
+        self.cases.extend(other.cases);
+    }
+
+    /// Applies results of failed tests that were run again, ones that passed now are flaky
+    pub fn retried(&mut self, retry: TestReport) {
+        for case in retry.cases {
+            let failed = self.cases.iter_mut().find(|v| {
+                v.status == TestStatus::Failed && v.name == case.name && v.suite == case.suite
+            });
+            match failed {
+                Some(failed) if case.status == TestStatus::Passed => {
+                    failed.status = TestStatus::Passed;
+                    failed.flaky = true;
+                }
+                // Output of last failure is the relevant one
+                Some(failed) => failed.output = case.output.or(failed.output.take()),
+                None => {}
+            }
+        }
+    }
+
+    /// Report of last test run in project, stored in `$XDG_STATE_HOME/ciri`
+    pub fn last_path(project: &Path) -> miette::Result<PathBuf> {
+        Ok(state_dir()?
+            .join("tests")
+            .join(format!("{}.json", project_key(project))))
+    }
+
+    /// Saved report, none when there's no report or it can't be parsed
+    pub fn load(path: &Path) -> miette::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path).into_diagnostic()?).ok())
+    }
+
+    pub fn save(&self, path: &Path) -> miette::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+        fs::write(path, serde_json::to_string(self).into_diagnostic()?).into_diagnostic()
+    }
+}
+
+/// Arguments of test runner that select only `cases`, none when manager can't select tests
+///
+/// They are passed the same way as extra arguments of `ciri test`
+pub fn filter_args(agent: &str, cases: &[&TestCase]) -> Option<Vec<String>> {
+    let names = cases.iter().map(|v| v.name.as_str());
+    match agent {
+        // Filters of libtest are alternatives
+        "cargo" => Some(
+            ["--exact"]
+                .into_iter()
+                .chain(names)
+                .map(str::to_owned)
+                .collect(),
+        ),
+        // Jest, vitest and bun match pattern against full name of test
+        "npm" | "pnpm" | "yarn" | "bun" => {
+            let names: Vec<String> = names.map(escape_regex).collect();
+            Some(vec!["-t".to_owned(), format!("^({})$", names.join("|"))])
+        }
+        // ScalaTest selects tests of suites by name with `-z`, arguments before `--` pick suites
+        "sbt" | "scala-cli" => {
+            let suites: Option<Vec<&str>> = cases.iter().map(|v| v.suite.as_deref()).collect();
+            let mut suites = suites.unwrap_or_default();
+            suites.sort();
+            suites.dedup();
+
+            let mut p: Vec<String> = if agent == "sbt" {
+                let suites = if suites.is_empty() { vec!["*"] } else { suites };
+                suites.into_iter().map(str::to_owned).collect()
+            } else {
+                suites
+                    .into_iter()
+                    .flat_map(|v| ["--test-only".to_owned(), v.to_owned()])
+                    .collect()
+            };
+            p.push("--".to_owned());
+            for name in names {
+                p.push("-z".to_owned());
+                // sbt splits arguments of a command on spaces unless they are quoted
+                p.push(if agent == "sbt" {
+                    format!("\"{}\"", name.replace('"', "\\\""))
+                } else {
+                    name.to_owned()
+                });
+            }
+            Some(p)
+        }
+        // XCTest names tests as `Target.Class/method`
+        "swift" => {
+            let names: Vec<String> = cases
+                .iter()
+                .map(|v| match &v.suite {
+                    Some(suite) => escape_regex(&format!("{}/{}", suite, v.name)),
+                    None => escape_regex(&v.name),
+                })
+                .collect();
+            Some(vec![
+                "--filter".to_owned(),
+                format!("^({})$", names.join("|")),
+            ])
+        }
+        // Busted takes Lua patterns, filters are alternatives
+        "luarocks" => Some(
+            names
+                .map(|v| {
+                    let escaped: String = v
+                        .chars()
+                        .flat_map(|c| match c {
+                            '^' | '$' | '(' | ')' | '%' | '.' | '[' | ']' | '*' | '+' | '-'
+                            | '?' => vec!['%', c],
+                            c => vec![c],
+                        })
+                        .collect();
+                    format!("--filter=^{}$", escaped)
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Escapes characters that have special meaning in regular expressions
+pub fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Display for TestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} passed", self.count(TestStatus::Passed))?;
+        let flaky = self.cases.iter().filter(|v| v.flaky).count();
+        if flaky > 0 {
+            write!(f, " ({} flaky)", flaky)?;
+        }
+        write!(
+            f,
+            ", {} failed, {} skipped",
+            self.count(TestStatus::Failed),
+            self.count(TestStatus::Skipped)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_test() {
+        let mut report = TestReport {
+            cases: vec![
+                TestCase::new("a", TestStatus::Passed),
+                TestCase::new("b", TestStatus::Failed),
+            ],
+        };
+        report.extend(TestReport {
+            cases: vec![TestCase {
+                suite: Some("src/lib.rs".to_owned()),
+                ..TestCase::new("c", TestStatus::Skipped)
+            }],
+        });
+
+        assert_eq!(report.to_string(), "1 passed, 1 failed, 1 skipped");
+        assert_eq!(
+            report
+                .failures()
+                .map(|v| v.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        assert_eq!(report.cases[2].full_name(), "src/lib.rs::c");
+    }
+
+    #[test]
+    fn retried_test() -> miette::Result<()> {
+        let failed = |name: &str| TestCase {
+            output: Some("first".to_owned()),
+            ..TestCase::new(name, TestStatus::Failed)
+        };
+        let mut report = TestReport {
+            cases: vec![
+                TestCase::new("a", TestStatus::Passed),
+                failed("b"),
+                failed("c"),
+            ],
+        };
+        report.retried(TestReport {
+            cases: vec![
+                TestCase::new("b", TestStatus::Passed),
+                TestCase {
+                    output: Some("second".to_owned()),
+                    ..TestCase::new("c", TestStatus::Failed)
+                },
+                TestCase::new("unknown", TestStatus::Passed),
+            ],
+        });
+
+        assert_eq!(
+            report.to_string(),
+            "2 passed (1 flaky), 1 failed, 0 skipped"
+        );
+        assert!(report.cases[1].flaky);
+        assert_eq!(report.cases[2].output.as_deref(), Some("second"));
+
+        let path = Path::new("/tmp/ciri/retried_test/report.json");
+        let _ = fs::remove_dir_all("/tmp/ciri/retried_test");
+        assert_eq!(TestReport::load(path)?, None);
+        report.save(path)?;
+        assert_eq!(TestReport::load(path)?, Some(report));
+        fs::remove_dir_all("/tmp/ciri/retried_test").into_diagnostic()?;
+        Ok(())
+    }
+
+    #[test]
+    fn filter_args_test() {
+        let a = TestCase {
+            suite: Some("PkgTests.MathTests".to_owned()),
+            ..TestCase::new("add", TestStatus::Failed)
+        };
+        let b = TestCase::new("math divides (1.5)", TestStatus::Failed);
+        let cases = vec![&a, &b];
+
+        assert_eq!(
+            filter_args("cargo", &cases),
+            Some(vec![
+                "--exact".to_owned(),
+                "add".to_owned(),
+                "math divides (1.5)".to_owned()
+            ])
+        );
+        assert_eq!(
+            filter_args("npm", &cases),
+            Some(vec![
+                "-t".to_owned(),
+                "^(add|math divides \\(1\\.5\\))$".to_owned()
+            ])
+        );
+        assert_eq!(
+            filter_args("swift", &cases),
+            Some(vec![
+                "--filter".to_owned(),
+                "^(PkgTests\\.MathTests/add|math divides \\(1\\.5\\))$".to_owned()
+            ])
+        );
+        assert_eq!(
+            filter_args("luarocks", &cases[1..]),
+            Some(vec!["--filter=^math divides %(1%.5%)$".to_owned()])
+        );
+        assert_eq!(
+            filter_args("sbt", &cases),
+            Some(vec![
+                "*".to_owned(),
+                "--".to_owned(),
+                "-z".to_owned(),
+                "\"add\"".to_owned(),
+                "-z".to_owned(),
+                "\"math divides (1.5)\"".to_owned()
+            ])
+        );
+        assert_eq!(
+            filter_args("scala-cli", &cases[..1]),
+            Some(vec![
+                "--test-only".to_owned(),
+                "PkgTests.MathTests".to_owned(),
+                "--".to_owned(),
+                "-z".to_owned(),
+                "add".to_owned()
+            ])
+        );
+        assert_eq!(filter_args("go", &cases), None);
+    }
+}
@@ -11,7 +11,7 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{Args, ColorChoice, Parser, Subcommand, ValueEnum};
@@ -19,9 +19,14 @@ use lazy_static::lazy_static;
 use miette::{bail, IntoDiagnostic};
 use serde::{Deserialize, Serialize};
 
-use self::args::package::{Add, Build, Clean, New, Remove, Run, Test, Update};
+use self::args::package::{Add, Build, Clean, History, New, Remove, Run, Start, Test, Update};
 use self::args::SystemSubCommands;
+use self::entities::env::EnvEntry;
+use self::entities::limits::LimitsConfig;
 use self::entities::managers::*;
+use self::entities::processes::Process;
+use self::entities::profile::Profile;
+use self::entities::tasks::{Hooks, Task};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, color = ColorChoice::Always)]
@@ -71,6 +76,15 @@ pub enum SubCommands {
     /// Cleans build files/directories
     #[clap(visible_alias = "c")]
     Clean(Clean),
+
+    /// Starts processes from Procfile or [processes] of .ciri.toml together
+    ///
+    /// `up` is already the alias of update, so this command is `start` or `s`
+    #[clap(visible_alias = "s")]
+    Start(Start),
+
+    /// Shows recorded builds, runs and tests with their duration trends
+    History(History),
 }
 
 #[derive(Args)]
@@ -97,8 +111,10 @@ pub enum PackageManagers {
     Gradle,
     Groovy,
     Java,
+    Just,
     Kotlin,
     Lua,
+    Make,
     Maven,
     Node,
     Npm,
@@ -130,8 +146,10 @@ impl PackageManagers {
             Self::Gradle,
             Self::Groovy,
             Self::Java,
+            Self::Just,
             Self::Kotlin,
             Self::Lua,
+            Self::Make,
             Self::Maven,
             Self::Node,
             Self::Npm,
@@ -158,9 +176,35 @@ impl PackageManagers {
             Self::Yarn => Ok(YARN_MANAGER),
             Self::Bun => Ok(BUN_MANAGER),
             Self::Gpp => Ok(GPP_MANAGER),
+            Self::Clangpp => Ok(CLANGPP_MANAGER),
+            Self::Lua => Ok(LUAROCKS_MANAGER),
+            // Projects without build definition are run as scala-cli sources
+            Self::Scala if Path::new("build.sbt").exists() => Ok(SBT_MANAGER),
+            Self::Scala => Ok(SCALA_CLI_MANAGER),
+            Self::Swift => Ok(SWIFT_MANAGER),
+            Self::Gcc => Ok(GCC_MANAGER),
+            Self::Clang => Ok(CLANG_MANAGER),
+            Self::Make => Ok(MAKE_MANAGER),
+            Self::Just => Ok(JUST_MANAGER),
             &_ => bail!("Package manager was not yet implemented"),
         }
     }
+
+    /// Swaps C/C++ compiler for the one from config, keeping the language
+    pub fn with_config(self, config: &Config) -> Self {
+        let Some(Ok(compiler)) = config.compiler.as_deref().map(<Self as FromStr>::from_str) else {
+            return self;
+        };
+
+        let clang = matches!(compiler, Self::Clang | Self::Clangpp);
+        match self {
+            Self::Gcc | Self::Clang if clang => Self::Clang,
+            Self::Gcc | Self::Clang => Self::Gcc,
+            Self::Gpp | Self::Clangpp if clang => Self::Clangpp,
+            Self::Gpp | Self::Clangpp => Self::Gpp,
+            _ => self,
+        }
+    }
 }
 
 impl FromStr for PackageManagers {
@@ -182,8 +226,10 @@ impl FromStr for PackageManagers {
             "gradle" => Ok(Self::Gradle),
             "groovy" => Ok(Self::Groovy),
             "java" => Ok(Self::Java),
+            "just" => Ok(Self::Just),
             "kotlin" => Ok(Self::Kotlin),
             "lua" => Ok(Self::Lua),
+            "make" => Ok(Self::Make),
             "maven" => Ok(Self::Maven),
             "node" => Ok(Self::Node),
             "npm" => Ok(Self::Npm),
@@ -207,19 +253,21 @@ impl Display for PackageManagers {
             Self::Bun => write!(f, "bun"),
             Self::Cargo => write!(f, "cargo"),
             Self::Clang => write!(f, "clang"),
-            Self::Clangpp => write!(f, "clangpp"),
+            Self::Clangpp => write!(f, "clang++"),
             Self::Composer => write!(f, "composer"),
             Self::Dart => write!(f, "dart"),
             Self::Deno => write!(f, "deno"),
             Self::Flutter => write!(f, "flutter"),
-            Self::Gpp => write!(f, "gpp"),
+            Self::Gpp => write!(f, "g++"),
             Self::Gcc => write!(f, "gcc"),
             Self::Go => write!(f, "go"),
             Self::Gradle => write!(f, "gradle"),
             Self::Groovy => write!(f, "groovy"),
             Self::Java => write!(f, "java"),
+            Self::Just => write!(f, "just"),
             Self::Kotlin => write!(f, "kotlin"),
             Self::Lua => write!(f, "lua"),
+            Self::Make => write!(f, "make"),
             Self::Maven => write!(f, "maven"),
             Self::Node => write!(f, "node"),
             Self::Npm => write!(f, "npm"),
@@ -239,10 +287,15 @@ impl Display for PackageManagers {
 lazy_static! {
     static ref LANGUAGES: HashMap<&'static str, Vec<&'static str>> = HashMap::from([
         (
-            "c",
+            "gcc",
             vec![
+                // Files
+                "meson.build",
+                "configure.ac",
+                "autogen.sh",
                 // Extensions
-                "c", "h",
+                "c",
+                "h",
             ],
         ),
         (
@@ -250,6 +303,9 @@ lazy_static! {
             vec![
                 // Files
                 "CMakeLists.txt",
+                "meson.build",
+                "configure.ac",
+                "autogen.sh",
                 // Extensions
                 "cpp",
                 "c++",
@@ -292,7 +348,6 @@ lazy_static! {
                 // Files
                 "pom.xml",
                 "build.gradle.kts",
-                "build.sbt",
                 ".java-version",
                 "deps.edn",
                 "project.clj",
@@ -321,6 +376,7 @@ lazy_static! {
                 ".lua-version",
                 // Directories/Extensions
                 "lua",
+                "rockspec",
             ],
         ),
         (
@@ -405,13 +461,16 @@ lazy_static! {
             vec![
                 // Files
                 "build.sbt",
+                "project.scala",
                 // Directories
                 ".metals",
+                ".scala-build",
                 // Extensions
                 "scalaenv",
                 "sbtenv",
                 "scala",
                 "sbt",
+                "sc",
             ],
         ),
         (
@@ -449,37 +508,72 @@ lazy_static! {
 
 pub trait Util {
     fn to_tuple(&self) -> miette::Result<(&str, &str)>;
+    fn to_args(&self) -> miette::Result<(&str, Vec<&str>)>;
 }
 
 impl Util for &str {
     fn to_tuple(&self) -> miette::Result<(&str, &str)> {
         let p = self.split_whitespace().collect::<Vec<_>>();
         Ok((
-            p.get(0).ok_or(Error::NoArgument).into_diagnostic()?,
+            p.first().ok_or(Error::NoArgument).into_diagnostic()?,
             p.get(1).ok_or(Error::NoArgument).into_diagnostic()?,
         ))
     }
+
+    fn to_args(&self) -> miette::Result<(&str, Vec<&str>)> {
+        let mut p = self.split_whitespace();
+        let program = p.next().ok_or(Error::NoArgument).into_diagnostic()?;
+        Ok((program, p.collect()))
+    }
 }
 
 #[derive(Debug)]
 enum Error {
     NoArgument,
+    NoHome,
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
             Self::NoArgument => write!(f, "Command doesn't have enough arguments"),
+            Self::NoHome => write!(f, "Home directory couldn't be found"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Config {
     pub bin_name: Option<String>,
     pub prefered_project_manager: Option<String>,
+    /// Compiler used for C and C++ projects (gcc, clang, g++ or clang++)
+    pub compiler: Option<String>,
+    /// Flags passed to C compiler when building single files
+    pub cflags: Option<Vec<String>>,
+    /// Flags passed to C++ compiler when building single files
+    pub cxxflags: Option<Vec<String>>,
+    /// Flags passed to linker when building single files
+    pub ldflags: Option<Vec<String>>,
+    /// Globs of files that builds without their own incremental support depend on
+    ///
+    /// Setting them also lets `pre_build` hooks be skipped while the files don't change
+    pub build_inputs: Option<Vec<String>>,
+    /// Globs of JUnit XML files written by test runners, they replace results parsed from output
+    pub test_reports: Option<Vec<String>>,
+    /// Variables passed to run and test, nested tables are selected with `--env-profile`
+    pub env: Option<HashMap<String, EnvEntry>>,
+    /// Default limits of run and test
+    pub limits: Option<LimitsConfig>,
+    /// Custom profiles selected with `--profile`
+    pub profiles: Option<HashMap<String, Profile>>,
+    /// Processes started together by `ciri start`, Procfile is used when not set
+    pub processes: Option<HashMap<String, Process>>,
+    /// Commands run before and after build, run, test and clean
+    pub hooks: Option<Hooks>,
+    /// Project tasks, ones named like subcommands replace manager's commands
+    pub tasks: Option<HashMap<String, Task>>,
 }
 
 impl Config {
@@ -487,6 +581,7 @@ impl Config {
         Self {
             bin_name,
             prefered_project_manager,
+            ..Default::default()
         }
     }
 
@@ -517,9 +612,89 @@ impl Config {
         let mut data: String = String::new();
         file.read_to_string(&mut data).into_diagnostic()?;
         let json: Self = toml::from_str(&data).into_diagnostic()?;
-        Ok(Self {
-            bin_name: json.bin_name,
-            prefered_project_manager: json.prefered_project_manager,
-        })
+        Ok(json)
+    }
+
+    /// Reads config or uses defaults if project doesn't have one
+    pub fn load() -> miette::Result<Self> {
+        if Path::new(".ciri.toml").exists() {
+            Self::read()
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Directory for files that can be safely removed, like binaries built from single files
+pub fn cache_dir() -> miette::Result<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// Directory for data that persists between runs but isn't worth backing up, like history
+pub fn state_dir() -> miette::Result<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> miette::Result<PathBuf> {
+    let dir = match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(
+            std::env::var_os("HOME")
+                .ok_or(Error::NoHome)
+                .into_diagnostic()?,
+        )
+        .join(fallback),
+    };
+    Ok(dir.join("ciri"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_managers_round_trip_test() {
+        for manager in PackageManagers::value_variants() {
+            assert_eq!(
+                &<PackageManagers as FromStr>::from_str(&manager.to_string()).unwrap(),
+                manager
+            );
+            assert_eq!(
+                &<PackageManagers as FromStr>::from_str(
+                    manager.to_possible_value().unwrap().get_name()
+                )
+                .unwrap(),
+                manager
+            );
+        }
+    }
+
+    #[test]
+    fn with_config_test() {
+        let mut config = Config::default();
+        assert_eq!(
+            PackageManagers::Gpp.with_config(&config),
+            PackageManagers::Gpp
+        );
+
+        config.compiler = Some("clang++".to_owned());
+        assert_eq!(
+            PackageManagers::Gpp.with_config(&config),
+            PackageManagers::Clangpp
+        );
+        assert_eq!(
+            PackageManagers::Gcc.with_config(&config),
+            PackageManagers::Clang
+        );
+        assert_eq!(
+            PackageManagers::Cargo.with_config(&config),
+            PackageManagers::Cargo
+        );
+
+        config.compiler = Some("gcc".to_owned());
+        assert_eq!(
+            PackageManagers::Clangpp.with_config(&config),
+            PackageManagers::Gpp
+        );
     }
 }
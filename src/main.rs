@@ -71,15 +71,26 @@ fn main() -> miette::Result<()> {
 
     // TODO: And check for watch utility. Native or third party
     if let Some(subsommands) = cli.subcommands {
-        match subsommands {
-            ciri::SubCommands::System(cmd) => package_subcommand(cmd)?,
-            ciri::SubCommands::New(args) => package::new(args)?,
-            ciri::SubCommands::Run(args) => package::run(args)?,
-            ciri::SubCommands::Build(args) => package::build(args)?,
-            ciri::SubCommands::Clean(_) => package::clean()?,
-
-            _ => todo!(),
-        }
+        let result = match subsommands {
+            ciri::SubCommands::System(cmd) => package_subcommand(cmd),
+            ciri::SubCommands::New(args) => package::new(args),
+            ciri::SubCommands::Run(args) => {
+                package::with_summary("run", args.json, || package::run(args))
+            }
+            ciri::SubCommands::Build(args) => {
+                package::with_summary("build", args.json, || package::build(args))
+            }
+            ciri::SubCommands::Test(args) => {
+                package::with_summary("test", args.json, || package::test(args))
+            }
+            ciri::SubCommands::Clean(args) => package::clean(args),
+            ciri::SubCommands::Add(args) => package::add(args),
+            ciri::SubCommands::Remove(args) => package::remove(args),
+            ciri::SubCommands::Update(args) => package::update(args),
+            ciri::SubCommands::Start(args) => package::start(args),
+            ciri::SubCommands::History(args) => package::history(args),
+        };
+        exit_with_child(result)?;
     } else {
         error!("No operation provided. (Use '-h' for help)");
     }
@@ -87,6 +98,24 @@ fn main() -> miette::Result<()> {
     Ok(())
 }
 
+/// Exits with the same code as failed child process so ciri can be used in scripts
+///
+/// Code is the one reported by summary, errors not caused by children exit with 1
+fn exit_with_child(result: miette::Result<()>) -> miette::Result<()> {
+    let Err(err) = result else {
+        return Ok(());
+    };
+
+    // Interrupted process was stopped on purpose, there is nothing to report
+    let interrupted = err
+        .downcast_ref::<package::ChildExit>()
+        .is_some_and(package::ChildExit::interrupted);
+    if !interrupted {
+        eprintln!("{:?}", err);
+    }
+    std::process::exit(package::exit_code(&err));
+}
+
 fn package_subcommand(cmd: System) -> miette::Result<()> {
     if let Some(subcommands) = cmd.subcommands {
         match subcommands {
@@ -0,0 +1,657 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{LabeledSpan, MietteDiagnostic, NamedSource, Report, Severity};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_till1};
+use nom::character::complete::{char, digit1, space0};
+use nom::combinator::{map, map_res, opt, rest, value};
+use nom::error::context;
+use nom::sequence::{delimited, preceded, separated_pair, tuple};
+use serde::{Deserialize, Serialize};
+
+use super::Res;
+
+/// Format of output produced by build tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `cargo --message-format=json`
+    CargoJson,
+    /// `file:line:col: error: message` of GCC and Clang
+    Gcc,
+    /// `file(line,col): error TS2322: message` of tsc
+    ///
+    /// Node managers have no build command, so it's only used through the library
+    Tsc,
+}
+
+impl OutputFormat {
+    /// Format of messages printed by builds of manager
+    pub fn for_manager(agent: &str) -> Option<Self> {
+        match agent {
+            "cargo" => Some(Self::CargoJson),
+            "gcc" | "clang" | "g++" | "clang++" => Some(Self::Gcc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+/// Place in source file that message points to, lines and columns start at 1
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// Number of bytes pointed to, when tool reports it
+    pub length: Option<usize>,
+}
+
+/// Error or warning reported by compiler
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompilerMessage {
+    pub level: Level,
+    pub message: String,
+    /// Like `E0308`, `TS2322` or `-Wunused-variable`
+    pub code: Option<String>,
+    pub location: Option<Location>,
+    /// Text shown next to the pointed code
+    pub label: Option<String>,
+    /// Notes and suggestions attached to message
+    pub help: Option<String>,
+}
+
+impl CompilerMessage {
+    fn new(level: Level, message: &str, location: Option<Location>) -> Self {
+        Self {
+            level,
+            message: message.to_owned(),
+            code: None,
+            location,
+            label: None,
+            help: None,
+        }
+    }
+
+    /// Diagnostic with snippet of source, paths are relative to `root`
+    ///
+    /// When file can't be read the location is added to help instead
+    pub fn to_report(&self, root: &Path) -> Report {
+        let severity = match self.level {
+            Level::Error => Severity::Error,
+            Level::Warning => Severity::Warning,
+            Level::Note => Severity::Advice,
+        };
+        let mut diagnostic = MietteDiagnostic::new(&self.message).with_severity(severity);
+        if let Some(code) = &self.code {
+            diagnostic = diagnostic.with_code(code);
+        }
+
+        let Some(location) = &self.location else {
+            return Report::new(self.with_help(diagnostic, None));
+        };
+        let Ok(source) = fs::read_to_string(root.join(&location.file)) else {
+            let at = format!(
+                "at {}:{}:{}",
+                location.file.display(),
+                location.line,
+                location.column
+            );
+            return Report::new(self.with_help(diagnostic, Some(at)));
+        };
+
+        let offset = offset(&source, location.line, location.column);
+        let length = location
+            .length
+            .unwrap_or_else(|| token_length(&source[offset..]))
+            .min(source.len() - offset);
+        let span = LabeledSpan::new_with_span(self.label.clone(), (offset, length));
+        Report::new(self.with_help(diagnostic.with_label(span), None)).with_source_code(
+            NamedSource::new(location.file.display().to_string(), source),
+        )
+    }
+
+    fn with_help(&self, diagnostic: MietteDiagnostic, extra: Option<String>) -> MietteDiagnostic {
+        let help = [self.help.clone(), extra]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        if help.is_empty() {
+            diagnostic
+        } else {
+            diagnostic.with_help(help.join("\n"))
+        }
+    }
+}
+
+/// Byte offset of line and column, clamped to the end of line
+fn offset(source: &str, line: usize, column: usize) -> usize {
+    let mut start = 0;
+    for (i, text) in source.split_inclusive('\n').enumerate() {
+        if i + 1 == line {
+            let content = text.trim_end_matches(['\n', '\r']);
+            let mut column = (column.max(1) - 1).min(content.len());
+            // Some tools count characters instead of bytes
+            while !content.is_char_boundary(column) {
+                column -= 1;
+            }
+            return start + column;
+        }
+        start += text.len();
+    }
+    source.len()
+}
+
+/// Length of identifier starting the text, or of its first character
+fn token_length(text: &str) -> usize {
+    let identifier = text
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(text.len());
+    match identifier {
+        0 => text.chars().next().map_or(0, char::len_utf8),
+        v => v,
+    }
+}
+
+fn number(input: &str) -> Res<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn level(input: &str) -> Res<&str, Level> {
+    context(
+        "Level",
+        alt((
+            value(Level::Error, alt((tag("fatal error"), tag("error")))),
+            value(Level::Warning, tag("warning")),
+            value(Level::Note, tag("note")),
+        )),
+    )(input)
+}
+
+/// `file:line:col: error: message`, column is missing in some messages
+fn gcc_header(input: &str) -> Res<&str, CompilerMessage> {
+    context(
+        "GCC Message",
+        map(
+            tuple((
+                take_till1(|c| c == ':'),
+                preceded(char(':'), number),
+                opt(preceded(char(':'), number)),
+                preceded(tag(": "), level),
+                preceded(tag(": "), rest),
+            )),
+            |(file, line, column, level, message): (&str, _, _, _, &str)| {
+                let location = Location {
+                    file: PathBuf::from(file),
+                    line,
+                    column: column.unwrap_or(1),
+                    length: None,
+                };
+                // Warnings end with flag that enables them, e.g. [-Wunused-variable]
+                let (message, code) = match message.rsplit_once(" [-W") {
+                    Some((message, flag)) if flag.ends_with(']') => {
+                        (message, Some(format!("-W{}", flag.trim_end_matches(']'))))
+                    }
+                    _ => (message, None),
+                };
+                CompilerMessage {
+                    code,
+                    ..CompilerMessage::new(level, message, Some(location))
+                }
+            },
+        ),
+    )(input)
+}
+
+/// `file(line,col): error TS2322: message` or `file:line:col - error TS2322: message`
+fn tsc_header(input: &str) -> Res<&str, CompilerMessage> {
+    let plain = tuple((
+        take_till1(|c| c == '('),
+        delimited(
+            char('('),
+            separated_pair(number, char(','), number),
+            tag("): "),
+        ),
+    ));
+    let pretty = tuple((
+        take_till1(|c| c == ':'),
+        delimited(
+            char(':'),
+            separated_pair(number, char(':'), number),
+            tag(" - "),
+        ),
+    ));
+
+    context(
+        "TSC Message",
+        map(
+            tuple((
+                alt((plain, pretty)),
+                level,
+                delimited(space0, preceded(tag("TS"), digit1), tag(": ")),
+                rest,
+            )),
+            |((file, (line, column)), level, code, message): ((&str, _), _, &str, &str)| {
+                let location = Location {
+                    file: PathBuf::from(file),
+                    line,
+                    column,
+                    length: None,
+                };
+                CompilerMessage {
+                    code: Some(format!("TS{}", code)),
+                    ..CompilerMessage::new(level, message, Some(location))
+                }
+            },
+        ),
+    )(input)
+}
+
+/// Line under snippet that points to code, like `  |     ^~~~`
+fn caret_length(line: &str) -> Option<usize> {
+    let marks = line.rsplit_once('|').map_or(line, |(_, v)| v).trim();
+    let valid = !marks.is_empty() && marks.chars().all(|c| matches!(c, '^' | '~' | ' '));
+    valid.then(|| marks.trim_end().len())
+}
+
+#[derive(Deserialize)]
+struct CargoLine {
+    reason: String,
+    message: Option<CargoMessage>,
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    message: String,
+    level: String,
+    code: Option<CargoCode>,
+    spans: Vec<CargoSpan>,
+    children: Vec<CargoMessage>,
+}
+
+#[derive(Deserialize)]
+struct CargoCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct CargoSpan {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    byte_start: usize,
+    byte_end: usize,
+    is_primary: bool,
+    label: Option<String>,
+    /// Lines of source the span covers
+    #[serde(default)]
+    text: Vec<CargoText>,
+}
+
+#[derive(Deserialize)]
+struct CargoText {
+    text: String,
+}
+
+impl CargoSpan {
+    /// Column counted in bytes, like other formats, cargo counts characters
+    fn byte_column(&self) -> usize {
+        match self.text.first() {
+            Some(line) => {
+                let before = line
+                    .text
+                    .chars()
+                    .take(self.column_start.saturating_sub(1))
+                    .map(char::len_utf8)
+                    .sum::<usize>();
+                before + 1
+            }
+            None => self.column_start,
+        }
+    }
+}
+
+fn cargo_message(line: &str) -> Option<CompilerMessage> {
+    let line: CargoLine = serde_json::from_str(line).ok()?;
+    let message = line.message.filter(|_| line.reason == "compiler-message")?;
+    let level = match message.level.as_str() {
+        "warning" => Level::Warning,
+        "note" | "help" => Level::Note,
+        v if v.starts_with("error") => Level::Error,
+        _ => return None,
+    };
+    // Summaries like "aborting due to 2 previous errors" only repeat the count
+    if message.spans.is_empty()
+        && (message.message.starts_with("aborting due to") || message.message.ends_with("emitted"))
+    {
+        return None;
+    }
+
+    let span = message.spans.iter().find(|v| v.is_primary);
+    let help = message
+        .children
+        .iter()
+        .map(|v| format!("{}: {}", v.level, v.message))
+        .collect::<Vec<_>>();
+    Some(CompilerMessage {
+        code: message.code.map(|v| v.code),
+        label: span.and_then(|v| v.label.clone()),
+        help: (!help.is_empty()).then(|| help.join("\n")),
+        ..CompilerMessage::new(
+            level,
+            &message.message,
+            span.map(|v| Location {
+                file: PathBuf::from(&v.file_name),
+                line: v.line_start,
+                column: v.byte_column(),
+                length: Some(v.byte_end.saturating_sub(v.byte_start)),
+            }),
+        )
+    })
+}
+
+/// Reads output line by line, so it can be shown while build is running
+///
+/// Lines that belong to messages are kept to be rendered again, others are returned back
+pub struct MessageParser {
+    format: OutputFormat,
+    messages: Vec<CompilerMessage>,
+    /// Whether previous lines belong to last message
+    in_message: bool,
+    /// Line after message that is part of it only if caret line follows
+    held: Option<String>,
+}
+
+impl MessageParser {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            messages: vec![],
+            in_message: false,
+            held: None,
+        }
+    }
+
+    /// Parses line of output, returns lines that aren't part of any message
+    pub fn push(&mut self, line: &str) -> Vec<String> {
+        match self.format {
+            OutputFormat::CargoJson => match cargo_message(line) {
+                Some(message) => {
+                    self.messages.push(message);
+                    vec![]
+                }
+                // Other JSON lines are artifacts and build script output
+                None if line.starts_with('{') => vec![],
+                None => vec![line.to_owned()],
+            },
+            OutputFormat::Gcc => self.push_gcc(line),
+            OutputFormat::Tsc => self.push_tsc(line),
+        }
+    }
+
+    fn push_gcc(&mut self, line: &str) -> Vec<String> {
+        let held = self.held.take();
+        if let Ok((_, message)) = gcc_header(line) {
+            self.messages.push(message);
+            self.in_message = true;
+            return held.into_iter().collect();
+        }
+
+        if self.in_message {
+            if let Some(length) = caret_length(line) {
+                if let Some(location) = self.messages.last_mut().and_then(|v| v.location.as_mut()) {
+                    location.length = Some(length.max(1));
+                }
+                self.in_message = false;
+                return vec![];
+            }
+            // GCC prefixes snippet with line number, Clang prints it as is
+            if line
+                .trim_start()
+                .split_once('|')
+                .is_some_and(|(number, _)| number.trim().chars().all(|c| c.is_ascii_digit()))
+            {
+                return vec![];
+            }
+            if held.is_none() {
+                self.held = Some(line.to_owned());
+                return vec![];
+            }
+        }
+
+        self.in_message = false;
+        held.into_iter().chain([line.to_owned()]).collect()
+    }
+
+    fn push_tsc(&mut self, line: &str) -> Vec<String> {
+        if let Ok((_, message)) = tsc_header(line) {
+            self.messages.push(message);
+            self.in_message = true;
+            return vec![];
+        }
+
+        if self.in_message {
+            // Chained explanations are indented under the message
+            if line.starts_with("  ") && !line.trim_start().starts_with('~') {
+                if let Some(message) = self.messages.last_mut() {
+                    message.message.push('\n');
+                    message.message.push_str(line.trim());
+                }
+                return vec![];
+            }
+            // Snippets of pretty output
+            let trimmed = line.trim_start();
+            if trimmed.is_empty()
+                || trimmed.starts_with('~')
+                || trimmed
+                    .split_once(' ')
+                    .is_some_and(|(v, _)| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+            {
+                return vec![];
+            }
+        }
+
+        self.in_message = false;
+        vec![line.to_owned()]
+    }
+
+    /// Remaining output lines and all parsed messages
+    pub fn finish(self) -> (Vec<String>, Vec<CompilerMessage>) {
+        (self.held.into_iter().collect(), self.messages)
+    }
+}
+
+/// Messages from whole output of a build
+pub fn parse_messages(format: OutputFormat, output: &str) -> Vec<CompilerMessage> {
+    let mut parser = MessageParser::new(format);
+    for line in output.lines() {
+        parser.push(line);
+    }
+    parser.finish().1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcc_test() {
+        let output = "t.c: In function 'main':
+t.c:3:13: warning: initialization of 'int' from 'char *' makes integer from pointer without a cast [-Wint-conversion]
+    3 |     int x = \"a\";
+      |             ^~~
+t.c:5:12: error: 'y' undeclared (first use in this function)
+    5 |     return y;
+      |            ^
+make: *** [Makefile:2: all] Error 1";
+
+        let mut parser = MessageParser::new(OutputFormat::Gcc);
+        let passed = output
+            .lines()
+            .flat_map(|v| parser.push(v))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            passed,
+            vec![
+                "t.c: In function 'main':",
+                "make: *** [Makefile:2: all] Error 1"
+            ]
+        );
+
+        let (rest, messages) = parser.finish();
+        assert!(rest.is_empty());
+        assert_eq!(
+            messages[0],
+            CompilerMessage {
+                code: Some("-Wint-conversion".to_owned()),
+                ..CompilerMessage::new(
+                    Level::Warning,
+                    "initialization of 'int' from 'char *' makes integer from pointer without a cast",
+                    Some(Location {
+                        file: PathBuf::from("t.c"),
+                        line: 3,
+                        column: 13,
+                        length: Some(3),
+                    })
+                )
+            }
+        );
+        assert_eq!(messages[1].level, Level::Error);
+        assert_eq!(messages[1].location.as_ref().unwrap().length, Some(1));
+    }
+
+    #[test]
+    fn clang_test() {
+        let output = "main.c:3:9: error: use of undeclared identifier 'y'
+    return y;
+           ^
+main.c:4:1: fatal error: expected '}'
+1 error generated.";
+
+        let mut parser = MessageParser::new(OutputFormat::Gcc);
+        let passed = output
+            .lines()
+            .flat_map(|v| parser.push(v))
+            .collect::<Vec<_>>();
+        assert!(passed.is_empty());
+        // Line after message without snippet is held until next one shows it isn't caret
+        let (rest, messages) = parser.finish();
+        assert_eq!(rest, vec!["1 error generated."]);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].message, "expected '}'");
+        assert_eq!(messages[1].location.as_ref().unwrap().length, None);
+    }
+
+    #[test]
+    fn tsc_test() {
+        let output =
+            "src/index.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+src/index.ts(5,1): error TS2345: Argument of type 'A' is not assignable to parameter of type 'B'.
+  Property 'x' is missing in type 'A'.
+src/other.ts:2:5 - warning TS6133: 'a' is declared but its value is never read.
+
+2 const a = 1;
+      ~
+
+Found 3 errors.";
+
+        let mut parser = MessageParser::new(OutputFormat::Tsc);
+        let passed = output
+            .lines()
+            .flat_map(|v| parser.push(v))
+            .collect::<Vec<_>>();
+        assert_eq!(passed, vec!["Found 3 errors."]);
+
+        let (_, messages) = parser.finish();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].code.as_deref(), Some("TS2322"));
+        assert_eq!(
+            messages[1].message,
+            "Argument of type 'A' is not assignable to parameter of type 'B'.\nProperty 'x' is missing in type 'A'."
+        );
+        assert_eq!(
+            messages[2].location,
+            Some(Location {
+                file: PathBuf::from("src/other.ts"),
+                line: 2,
+                column: 5,
+                length: None
+            })
+        );
+    }
+
+    #[test]
+    fn cargo_test() {
+        let output = r#"{"reason":"compiler-artifact","package_id":"a"}
+{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":{"code":"E0308","explanation":null},"spans":[{"file_name":"src/main.rs","line_start":2,"line_end":2,"column_start":18,"column_end":21,"byte_start":28,"byte_end":31,"is_primary":true,"label":"expected `i32`, found `&str`"}],"children":[{"message":"try using a conversion method","level":"help","code":null,"spans":[],"children":[]}],"rendered":"error[E0308]"}}
+{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","level":"error","code":null,"spans":[],"children":[],"rendered":""}}
+{"reason":"build-finished","success":false}"#;
+
+        let messages = parse_messages(OutputFormat::CargoJson, output);
+        assert_eq!(
+            messages,
+            vec![CompilerMessage {
+                code: Some("E0308".to_owned()),
+                label: Some("expected `i32`, found `&str`".to_owned()),
+                help: Some("help: try using a conversion method".to_owned()),
+                ..CompilerMessage::new(
+                    Level::Error,
+                    "mismatched types",
+                    Some(Location {
+                        file: PathBuf::from("src/main.rs"),
+                        line: 2,
+                        column: 18,
+                        length: Some(3),
+                    })
+                )
+            }]
+        );
+
+        // Columns after multibyte characters are turned into bytes
+        let output = r#"{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":null,"spans":[{"file_name":"src/main.rs","line_start":2,"line_end":2,"column_start":18,"column_end":21,"byte_start":30,"byte_end":33,"is_primary":true,"label":null,"text":[{"text":"    let é: i32 = \"a\";","highlight_start":18,"highlight_end":21}]}],"children":[],"rendered":""}}"#;
+        let messages = parse_messages(OutputFormat::CargoJson, output);
+        assert_eq!(messages[0].location.as_ref().map(|v| v.column), Some(19));
+
+        assert_eq!(
+            MessageParser::new(OutputFormat::CargoJson).push("   Compiling ciri"),
+            vec!["   Compiling ciri"]
+        );
+    }
+
+    #[test]
+    fn to_report_test() {
+        let dir = Path::new("/tmp/ciri/compiler_test");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("main.c"), "int main() {\n    return y;\n}\n").unwrap();
+
+        let message = CompilerMessage::new(
+            Level::Error,
+            "'y' undeclared",
+            Some(Location {
+                file: PathBuf::from("main.c"),
+                line: 2,
+                column: 12,
+                length: None,
+            }),
+        );
+        let report = message.to_report(dir);
+        let label = report.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (24, 1));
+        assert!(report.source_code().is_some());
+
+        let report = message.to_report(Path::new("/nonexistent"));
+        assert_eq!(report.help().unwrap().to_string(), "at main.c:2:12");
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(offset("ab\ncd\n", 2, 10), 5);
+        assert_eq!(token_length("foo_1 + 2"), 5);
+        assert_eq!(token_length("+ 2"), 1);
+    }
+}
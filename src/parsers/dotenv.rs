@@ -0,0 +1,202 @@
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take_till};
+use nom::character::complete::{
+    alpha1, alphanumeric1, anychar, char, multispace1, not_line_ending, one_of, space0, space1,
+};
+use nom::combinator::{all_consuming, map, not, opt, recognize, value};
+use nom::error::context;
+use nom::multi::{many0, many0_count};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+
+use super::Res;
+
+/// Piece of a value, variables are expanded after the whole file is read
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvPart {
+    Literal(String),
+    Variable(String),
+}
+
+/// Joins parts, looking up values of variables. Unknown variables are empty
+pub fn expand(parts: &[EnvPart], lookup: impl Fn(&str) -> Option<String>) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            EnvPart::Literal(v) => v.to_owned(),
+            EnvPart::Variable(name) => lookup(name).unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn comment(input: &str) -> Res<&str, &str> {
+    context("Comment", preceded(char('#'), not_line_ending))(input)
+}
+
+fn ws(input: &str) -> Res<&str, ()> {
+    value((), many0_count(alt((multispace1, comment))))(input)
+}
+
+fn name(input: &str) -> Res<&str, &str> {
+    context(
+        "Name",
+        recognize(pair(
+            alt((alpha1, tag("_"))),
+            many0_count(alt((alphanumeric1, tag("_")))),
+        )),
+    )(input)
+}
+
+fn variable(input: &str) -> Res<&str, EnvPart> {
+    context(
+        "Variable",
+        map(
+            preceded(
+                char('$'),
+                alt((delimited(char('{'), name, char('}')), name)),
+            ),
+            |v| EnvPart::Variable(v.to_owned()),
+        ),
+    )(input)
+}
+
+fn single_quoted(input: &str) -> Res<&str, Vec<EnvPart>> {
+    context(
+        "Single quoted",
+        map(
+            delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+            |v: &str| vec![EnvPart::Literal(v.to_owned())],
+        ),
+    )(input)
+}
+
+fn escaped(input: &str) -> Res<&str, EnvPart> {
+    map(preceded(char('\\'), anychar), |c| {
+        EnvPart::Literal(match c {
+            'n' => "\n".to_owned(),
+            't' => "\t".to_owned(),
+            'r' => "\r".to_owned(),
+            c => c.to_string(),
+        })
+    })(input)
+}
+
+fn double_quoted(input: &str) -> Res<&str, Vec<EnvPart>> {
+    context(
+        "Double quoted",
+        delimited(
+            char('"'),
+            many0(alt((
+                variable,
+                escaped,
+                map(is_not("\"\\$"), |v: &str| EnvPart::Literal(v.to_owned())),
+                map(char('$'), |_| EnvPart::Literal("$".to_owned())),
+            ))),
+            char('"'),
+        ),
+    )(input)
+}
+
+/// Unquoted value with variables, used for values from config too
+pub fn unquoted(input: &str) -> Res<&str, Vec<EnvPart>> {
+    context(
+        "Unquoted",
+        many0(alt((
+            variable,
+            map(is_not("$"), |v: &str| EnvPart::Literal(v.to_owned())),
+            map(char('$'), |_| EnvPart::Literal("$".to_owned())),
+        ))),
+    )(input)
+}
+
+/// Rest of line without trailing comment and whitespace
+fn unquoted_line(input: &str) -> Res<&str, Vec<EnvPart>> {
+    let (rest, line) = not_line_ending(input)?;
+    let line = line
+        .find(" #")
+        .or_else(|| line.find("\t#"))
+        .map_or(line, |i| &line[..i])
+        .trim_end();
+    let (_, parts) = all_consuming(unquoted)(line)?;
+    Ok((rest, parts))
+}
+
+fn assignment(input: &str) -> Res<&str, (String, Vec<EnvPart>)> {
+    context(
+        "Assignment",
+        map(
+            tuple((
+                opt(pair(tag("export"), space1)),
+                name,
+                space0,
+                char('='),
+                space0,
+                alt((
+                    terminated(single_quoted, pair(space0, opt(comment))),
+                    terminated(double_quoted, pair(space0, opt(comment))),
+                    // Unterminated quotes aren't taken as literal value
+                    preceded(not(one_of("\"'")), unquoted_line),
+                )),
+            )),
+            |(_, key, _, _, _, parts)| (key.to_owned(), parts),
+        ),
+    )(input)
+}
+
+/// Parses `.env` file into variables in order of appearance
+pub fn dotenv_parser(input: &str) -> Res<&str, Vec<(String, Vec<EnvPart>)>> {
+    context(
+        "Dotenv",
+        all_consuming(delimited(ws, many0(terminated(assignment, ws)), ws)),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<(String, String)> {
+        let (_, vars) = dotenv_parser(input).unwrap();
+        vars.into_iter()
+            .map(|(k, v)| (k, expand(&v, |name| Some(format!("<{}>", name)))))
+            .collect()
+    }
+
+    #[test]
+    fn values_test() {
+        assert_eq!(
+            parse(
+                r#"
+# comment
+PLAIN=value
+export EXPORTED = spaced value # comment
+EMPTY=
+SINGLE='no $EXPANSION # here'
+DOUBLE="line\nbreak \"quoted\" # kept" # comment
+"#
+            ),
+            vec![
+                ("PLAIN".to_owned(), "value".to_owned()),
+                ("EXPORTED".to_owned(), "spaced value".to_owned()),
+                ("EMPTY".to_owned(), "".to_owned()),
+                ("SINGLE".to_owned(), "no $EXPANSION # here".to_owned()),
+                (
+                    "DOUBLE".to_owned(),
+                    "line\nbreak \"quoted\" # kept".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn expansion_test() {
+        assert_eq!(
+            parse("URL=http://${HOST}:$PORT/a$\nQUOTED=\"${USER}s\""),
+            vec![
+                ("URL".to_owned(), "http://<HOST>:<PORT>/a$".to_owned()),
+                ("QUOTED".to_owned(), "<USER>s".to_owned()),
+            ]
+        );
+        assert!(dotenv_parser("NOT VALID").is_err());
+        assert!(dotenv_parser("KEY=\"unterminated").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use miette::miette;
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take_till1, take_until, take_while1};
+use nom::character::complete::{char, multispace0, multispace1};
+use nom::combinator::{all_consuming, map, opt};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+
+use super::Res;
+use crate::entities::test_report::{TestCase, TestReport, TestStatus};
+
+/// Element of XML document, only what JUnit reports need
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|v| match v {
+            Node::Element(v) => Some(v),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|v| match v {
+                Node::Text(v) => Some(v.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Replaces predefined and numeric character references
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|v| u32::from_str_radix(v, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(decoded) => {
+                result.push(decoded);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn name(input: &str) -> Res<&str, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'))(input)
+}
+
+fn attribute(input: &str) -> Res<&str, (String, String)> {
+    map(
+        separated_pair(
+            name,
+            delimited(multispace0, char('='), multispace0),
+            alt((
+                delimited(char('"'), opt(is_not("\"")), char('"')),
+                delimited(char('\''), opt(is_not("'")), char('\'')),
+            )),
+        ),
+        |(k, v)| (k.to_owned(), unescape(v.unwrap_or_default())),
+    )(input)
+}
+
+/// Declarations, comments and processing instructions, which are skipped
+fn misc(input: &str) -> Res<&str, &str> {
+    alt((
+        delimited(tag("<!--"), take_until("-->"), tag("-->")),
+        delimited(tag("<?"), take_until("?>"), tag("?>")),
+        delimited(tag("<!DOCTYPE"), take_until(">"), char('>')),
+    ))(input)
+}
+
+fn content(input: &str) -> Res<&str, Vec<Node>> {
+    map(
+        many0(alt((
+            map(element, Node::Element),
+            map(
+                delimited(tag("<![CDATA["), take_until("]]>"), tag("]]>")),
+                |v: &str| Node::Text(v.to_owned()),
+            ),
+            map(misc, |_| Node::Text(String::new())),
+            map(take_till1(|c| c == '<'), |v| Node::Text(unescape(v))),
+        ))),
+        |nodes| {
+            nodes
+                .into_iter()
+                .filter(|v| !matches!(v, Node::Text(v) if v.is_empty()))
+                .collect()
+        },
+    )(input)
+}
+
+fn element(input: &str) -> Res<&str, Element> {
+    let (input, (name, attributes)) = preceded(
+        char('<'),
+        pair(name, many0(preceded(multispace1, attribute))),
+    )(input)?;
+    let (input, children) = preceded(
+        multispace0,
+        alt((
+            map(tag("/>"), |_| vec![]),
+            delimited(
+                char('>'),
+                content,
+                tuple((tag("</"), tag(name), multispace0, char('>'))),
+            ),
+        )),
+    )(input)?;
+
+    Ok((
+        input,
+        Element {
+            name: name.to_owned(),
+            attributes,
+            children,
+        },
+    ))
+}
+
+fn document(input: &str) -> Res<&str, Element> {
+    context(
+        "JUnit XML",
+        all_consuming(delimited(
+            many0(alt((misc, multispace1))),
+            element,
+            terminated(many0(alt((misc, multispace1))), multispace0),
+        )),
+    )(input)
+}
+
+/// Report from JUnit XML written by jest-junit, vitest, pytest, ctest and others
+pub fn parse_junit(xml: &str) -> miette::Result<TestReport> {
+    let xml = xml.trim_start_matches('\u{feff}');
+    let (_, root) = document(xml).map_err(|e| miette!("Invalid JUnit XML: {}", e))?;
+
+    let mut report = TestReport::default();
+    collect(&root, None, &mut report);
+    Ok(report)
+}
+
+fn collect(element: &Element, suite: Option<&str>, report: &mut TestReport) {
+    if element.name == "testcase" {
+        report.cases.push(test_case(element, suite));
+        return;
+    }
+
+    let suite = match element.name.as_str() {
+        "testsuite" => element.attribute("name").or(suite),
+        _ => suite,
+    };
+    for child in element.elements() {
+        collect(child, suite, report);
+    }
+}
+
+fn test_case(element: &Element, suite: Option<&str>) -> TestCase {
+    let name = element.attribute("name").unwrap_or_default();
+    // Some tools repeat name as classname, enclosing suite says more then
+    let suite = element
+        .attribute("classname")
+        .filter(|v| !v.is_empty() && *v != name)
+        .or(suite);
+
+    let mut status = TestStatus::Passed;
+    let mut output = vec![];
+    for child in element.elements() {
+        match child.name.as_str() {
+            "failure" | "error" => status = TestStatus::Failed,
+            "skipped" if status != TestStatus::Failed => status = TestStatus::Skipped,
+            "system-out" | "system-err" => {}
+            _ => continue,
+        }
+        let text = child.text().trim().to_owned();
+        // Message is often the first line of text
+        if let Some(message) = child.attribute("message").filter(|v| !text.starts_with(v)) {
+            output.push(message.to_owned());
+        }
+        output.push(text);
+    }
+    output.retain(|v| !v.is_empty());
+    output.dedup();
+
+    TestCase {
+        suite: suite.map(str::to_owned),
+        duration: element.attribute("time").and_then(|v| v.parse().ok()),
+        output: (status != TestStatus::Passed && !output.is_empty()).then(|| output.join("\n")),
+        ..TestCase::new(name, status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn element_test() {
+        let (_, parsed) =
+            element("<a x=\"1 &amp; 2\" y='3'>t&lt;<b/><![CDATA[<raw>]]><!-- c --></a >").unwrap();
+        assert_eq!(parsed.attribute("x"), Some("1 & 2"));
+        assert_eq!(parsed.attribute("y"), Some("3"));
+        assert_eq!(parsed.elements().count(), 1);
+        assert_eq!(parsed.text(), "t<<raw>");
+        assert!(element("<a></b>").is_err());
+        assert_eq!(unescape("&#65;&#x42;&unknown; &"), "AB&unknown; &");
+    }
+
+    #[test]
+    fn junit_test() -> miette::Result<()> {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- generated by pytest -->
+<testsuites>
+  <testsuite name="pytest" tests="4" failures="1" skipped="1">
+    <testcase classname="tests.test_math" name="test_add" time="0.010"/>
+    <testcase classname="tests.test_math" name="test_div" time="0.5">
+      <failure message="assert 1 == 2">def test_div():
+&gt;       assert 1 == 2</failure>
+      <system-out>dividing</system-out>
+    </testcase>
+    <testcase classname="tests.test_math" name="test_pow">
+      <skipped type="pytest.skip" message="not ready" />
+    </testcase>
+    <testcase classname="unit" name="unit" time="0.2">
+      <error message="segfault"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        let report = parse_junit(xml)?;
+        assert_eq!(report.to_string(), "1 passed, 2 failed, 1 skipped");
+        assert_eq!(report.cases[0].full_name(), "tests.test_math::test_add");
+        assert_eq!(report.cases[0].duration, Some(0.01));
+        assert_eq!(report.cases[0].output, None);
+        assert_eq!(
+            report.cases[1].output.as_deref(),
+            Some("assert 1 == 2\ndef test_div():\n>       assert 1 == 2\ndividing")
+        );
+        assert_eq!(report.cases[2].output.as_deref(), Some("not ready"));
+        assert_eq!(report.cases[3].full_name(), "pytest::unit");
+
+        assert!(parse_junit("<testsuite>").is_err());
+        Ok(())
+    }
+}
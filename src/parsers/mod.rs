@@ -6,7 +6,12 @@ use nom::error::{context, VerboseError};
 use nom::multi::many1;
 use nom::IResult;
 
+pub mod compiler;
+pub mod dotenv;
+pub mod junit;
+pub mod rockspec;
 pub mod system;
+pub mod test_output;
 pub mod version;
 
 type Res<T, U> = IResult<T, U, VerboseError<T>>;
@@ -0,0 +1,261 @@
+use nom::branch::alt;
+use nom::bytes::complete::{escaped_transform, is_not, tag, take_until};
+use nom::character::complete::{alpha1, alphanumeric1, char, multispace1, not_line_ending};
+use nom::combinator::{all_consuming, map, opt, recognize, value};
+use nom::error::context;
+use nom::multi::{many0, many0_count, separated_list0};
+use nom::number::complete::double;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+
+use super::Res;
+
+/// Subset of lua values that can appear in a rockspec
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// Table entries in order of appearance. Positional entries have no key
+    Table(Vec<(Option<String>, LuaValue)>),
+}
+
+impl LuaValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Get value of a named table field
+    pub fn get(&self, key: &str) -> Option<&LuaValue> {
+        match self {
+            Self::Table(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_deref() == Some(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Get string entries of a table, both named and positional
+    pub fn entries(&self) -> Vec<(Option<String>, String)> {
+        match self {
+            Self::Table(entries) => entries
+                .iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_owned())))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+fn comment(input: &str) -> Res<&str, &str> {
+    context(
+        "Comment",
+        preceded(
+            tag("--"),
+            alt((
+                delimited(tag("[["), take_until("]]"), tag("]]")),
+                not_line_ending,
+            )),
+        ),
+    )(input)
+}
+
+fn ws(input: &str) -> Res<&str, ()> {
+    value((), many0_count(alt((multispace1, comment))))(input)
+}
+
+fn identifier(input: &str) -> Res<&str, &str> {
+    context(
+        "Identifier",
+        recognize(pair(
+            alt((alpha1, tag("_"))),
+            many0_count(alt((alphanumeric1, tag("_")))),
+        )),
+    )(input)
+}
+
+fn quoted(quote: char) -> impl FnMut(&str) -> Res<&str, String> {
+    move |input| {
+        let not_quote = if quote == '"' { "\"\\\n" } else { "'\\\n" };
+        delimited(
+            char(quote),
+            map(
+                opt(escaped_transform(
+                    is_not(not_quote),
+                    '\\',
+                    alt((
+                        value("\\", tag("\\")),
+                        value("\"", tag("\"")),
+                        value("'", tag("'")),
+                        value("\n", tag("n")),
+                        value("\t", tag("t")),
+                    )),
+                )),
+                Option::unwrap_or_default,
+            ),
+            char(quote),
+        )(input)
+    }
+}
+
+fn string(input: &str) -> Res<&str, String> {
+    context(
+        "String",
+        alt((
+            quoted('"'),
+            quoted('\''),
+            map(
+                delimited(tag("[["), take_until("]]"), tag("]]")),
+                |v: &str| v.strip_prefix('\n').unwrap_or(v).to_owned(),
+            ),
+        )),
+    )(input)
+}
+
+fn table_key(input: &str) -> Res<&str, String> {
+    context(
+        "Table Key",
+        alt((
+            delimited(pair(char('['), ws), string, pair(ws, char(']'))),
+            map(identifier, ToOwned::to_owned),
+        )),
+    )(input)
+}
+
+fn table_field(input: &str) -> Res<&str, (Option<String>, LuaValue)> {
+    context(
+        "Table Field",
+        alt((
+            map(
+                separated_pair(table_key, tuple((ws, char('='), ws)), lua_value),
+                |(k, v)| (Some(k), v),
+            ),
+            map(lua_value, |v| (None, v)),
+        )),
+    )(input)
+}
+
+fn table(input: &str) -> Res<&str, Vec<(Option<String>, LuaValue)>> {
+    context(
+        "Table",
+        delimited(
+            pair(char('{'), ws),
+            terminated(
+                separated_list0(tuple((ws, alt((char(','), char(';'))), ws)), table_field),
+                opt(pair(ws, alt((char(','), char(';'))))),
+            ),
+            pair(ws, char('}')),
+        ),
+    )(input)
+}
+
+pub fn lua_value(input: &str) -> Res<&str, LuaValue> {
+    context(
+        "Value",
+        alt((
+            map(string, LuaValue::String),
+            map(table, LuaValue::Table),
+            value(LuaValue::Bool(true), tag("true")),
+            value(LuaValue::Bool(false), tag("false")),
+            value(LuaValue::Nil, tag("nil")),
+            map(double, LuaValue::Number),
+        )),
+    )(input)
+}
+
+fn assignment(input: &str) -> Res<&str, (String, LuaValue)> {
+    context(
+        "Assignment",
+        map(
+            separated_pair(identifier, tuple((ws, char('='), ws)), lua_value),
+            |(k, v)| (k.to_owned(), v),
+        ),
+    )(input)
+}
+
+/// Parses top level assignments of a rockspec file
+pub fn rockspec_parser(input: &str) -> Res<&str, Vec<(String, LuaValue)>> {
+    context(
+        "Rockspec",
+        all_consuming(delimited(ws, many0(terminated(assignment, ws)), ws)),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_test() {
+        assert_eq!(string(r#""ciri""#), Ok(("", "ciri".to_owned())));
+        assert_eq!(string("'ciri'"), Ok(("", "ciri".to_owned())));
+        assert_eq!(string(r#""a \"b\"""#), Ok(("", "a \"b\"".to_owned())));
+        assert_eq!(string("\"\""), Ok(("", "".to_owned())));
+        assert_eq!(
+            string("[[\nlong\nstring]]"),
+            Ok(("", "long\nstring".to_owned()))
+        );
+    }
+
+    #[test]
+    fn table_test() {
+        assert_eq!(
+            lua_value(r#"{ "lua >= 5.1", ["a.b"] = "src/a/b.lua"; c = { true }, }"#),
+            Ok((
+                "",
+                LuaValue::Table(vec![
+                    (None, LuaValue::String("lua >= 5.1".to_owned())),
+                    (
+                        Some("a.b".to_owned()),
+                        LuaValue::String("src/a/b.lua".to_owned())
+                    ),
+                    (
+                        Some("c".to_owned()),
+                        LuaValue::Table(vec![(None, LuaValue::Bool(true))])
+                    ),
+                ])
+            ))
+        );
+        assert_eq!(lua_value("{}"), Ok(("", LuaValue::Table(vec![]))));
+    }
+
+    #[test]
+    fn rockspec_parser_test() {
+        let input = r#"-- generated
+package = "ciri"
+version = "1.0.0-1"
+source = {
+   url = "git+https://github.com/ponurakk/ciri.git" -- trailing comment
+}
+description = {
+   summary = "Everything you need in one place",
+   license = "MIT",
+}
+build = {
+   type = "builtin",
+   install = { bin = { ciri = "src/main.lua" } },
+}
+"#;
+        let (rest, parsed) = rockspec_parser(input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(
+            parsed[0],
+            ("package".to_owned(), LuaValue::String("ciri".to_owned()))
+        );
+        assert_eq!(
+            parsed[4]
+                .1
+                .get("install")
+                .and_then(|v| v.get("bin"))
+                .map(LuaValue::entries),
+            Some(vec![(Some("ciri".to_owned()), "src/main.lua".to_owned())])
+        );
+
+        assert!(rockspec_parser("package = ").is_err());
+    }
+}
@@ -75,7 +75,11 @@ fn field(input: &str) -> Res<&str, (String, Option<String>, bool)> {
                         format!(
                             "{}{}{}",
                             v.0,
-                            if v.1.unwrap_or("") != "" { " " } else { "" },
+                            if !v.1.unwrap_or("").is_empty() {
+                                " "
+                            } else {
+                                ""
+                            },
                             v.1.unwrap_or("")
                         )
                     }),
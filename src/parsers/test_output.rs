@@ -0,0 +1,525 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{char, digit1, space0, space1};
+use nom::combinator::{map, map_res, opt, rest, value};
+use nom::error::context;
+use nom::sequence::{delimited, preceded, terminated, tuple};
+use serde::Deserialize;
+
+use super::Res;
+use crate::entities::test_report::{TestCase, TestReport, TestStatus};
+
+fn number(input: &str) -> Res<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// `test name ... ok`, with `<0.001s>` when libtest reports time
+fn libtest_line(input: &str) -> Res<&str, (&str, TestStatus, Option<f64>)> {
+    context(
+        "Libtest Result",
+        tuple((
+            delimited(tag("test "), take_until(" ... "), tag(" ... ")),
+            alt((
+                value(TestStatus::Passed, tag("ok")),
+                value(TestStatus::Failed, tag("FAILED")),
+                value(TestStatus::Skipped, tag("ignored")),
+            )),
+            opt(preceded(
+                space1,
+                delimited(
+                    char('<'),
+                    map_res(take_until("s>"), str::parse::<f64>),
+                    tag("s>"),
+                ),
+            )),
+        )),
+    )(input)
+}
+
+/// `    not ok 2 - name # SKIP reason`, returns indentation, whether it passed, number and the rest
+fn tap_line(input: &str) -> Res<&str, (usize, bool, usize, &str)> {
+    context(
+        "TAP Result",
+        tuple((
+            map(space0, str::len),
+            map(opt(tag("not ")), |v| v.is_none()),
+            preceded(tuple((tag("ok"), space1)), number),
+            rest,
+        )),
+    )(input)
+}
+
+/// `1/2 Test #1: name ......   Passed    0.01 sec`, returns the part after the number
+fn ctest_line(input: &str) -> Res<&str, &str> {
+    context(
+        "CTest Result",
+        preceded(
+            tuple((
+                space0,
+                terminated(number, char('/')),
+                number,
+                tag(" Test"),
+                space1,
+                char('#'),
+                number,
+                tag(": "),
+            )),
+            rest,
+        ),
+    )(input)
+}
+
+#[derive(Deserialize)]
+struct LibtestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    stdout: Option<String>,
+    exec_time: Option<f64>,
+}
+
+/// Lines collected as output of a case
+enum Capture {
+    /// `---- name stdout ----` sections after libtest failures
+    Libtest,
+    /// TAP result that can be followed by YAML block
+    TapResult,
+    /// YAML block after TAP result
+    Tap,
+    /// Output printed by ctest after failed test
+    Ctest,
+}
+
+/// Reads output of test runner line by line, so tests can be shown while running
+///
+/// Format is detected by every line, so output of several runners can be mixed, e.g. unit and
+/// doc tests of cargo. Supported are libtest (text and JSON), TAP and ctest
+#[derive(Default)]
+pub struct TestOutputParser {
+    report: TestReport,
+    /// Test binary that cargo is running
+    suite: Option<String>,
+    /// Lines of output and case they belong to, none when case is ignored
+    capture: Option<(Capture, Option<usize>, Vec<String>)>,
+    /// Indentation of last TAP result, ones less indented are parents of subtests
+    tap_indent: Option<usize>,
+    /// Names from `# Subtest:` comments with their indentation
+    subtests: Vec<(usize, String)>,
+}
+
+impl TestOutputParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.push_capture(line) {
+            return;
+        }
+
+        if let Some(suite) = cargo_suite(line) {
+            self.suite = Some(suite);
+        } else if line.starts_with('{') {
+            self.push_json(line);
+        } else if let Ok((_, (name, status, duration))) = libtest_line(line) {
+            self.add(TestCase {
+                duration,
+                ..TestCase::new(name, status)
+            });
+        } else if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|v| v.strip_suffix(" stdout ----"))
+        {
+            let index = self.find(name);
+            self.capture = Some((Capture::Libtest, index, vec![]));
+        } else if let Ok((_, (indent, passed, number, description))) = tap_line(line) {
+            self.push_tap(indent, passed, number, description);
+        } else if let Some(name) = line.trim_start().strip_prefix("# Subtest: ") {
+            let indent = line.len() - line.trim_start().len();
+            self.subtests.retain(|(v, _)| *v < indent);
+            self.subtests.push((indent, name.to_owned()));
+        } else if let Ok((_, result)) = ctest_line(line) {
+            self.push_ctest(result);
+        }
+    }
+
+    /// Adds line to captured output, returns whether it belonged there
+    fn push_capture(&mut self, line: &str) -> bool {
+        let Some((capture, _, lines)) = &mut self.capture else {
+            return false;
+        };
+
+        let (ended, consumed) = match capture {
+            Capture::Libtest => (
+                line == "failures:"
+                    || line.starts_with("test result:")
+                    || (line.starts_with("---- ") && line.ends_with(" ----")),
+                false,
+            ),
+            Capture::TapResult if line.trim() == "---" => {
+                *capture = Capture::Tap;
+                return true;
+            }
+            Capture::TapResult => (true, false),
+            Capture::Tap => (line.trim() == "...", true),
+            Capture::Ctest => (
+                ctest_line(line).is_ok()
+                    || line.trim_start().starts_with("Start ")
+                    || line.contains("% tests passed"),
+                false,
+            ),
+        };
+
+        if !ended {
+            lines.push(line.to_owned());
+            return true;
+        }
+        self.end_capture();
+        consumed
+    }
+
+    fn end_capture(&mut self) {
+        let Some((capture, Some(index), lines)) = self.capture.take() else {
+            return;
+        };
+        let case = &mut self.report.cases[index];
+
+        let output = match capture {
+            Capture::TapResult | Capture::Tap => {
+                let indent = lines
+                    .iter()
+                    .filter(|v| !v.trim().is_empty())
+                    .map(|v| v.len() - v.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                let lines: Vec<&str> = lines
+                    .iter()
+                    .map(|v| v.get(indent..).unwrap_or(""))
+                    .collect();
+                // Node adds duration to every result
+                if let Some(ms) = lines
+                    .iter()
+                    .find_map(|v| v.strip_prefix("duration_ms: "))
+                    .and_then(|v| v.trim().parse::<f64>().ok())
+                {
+                    case.duration = Some(ms / 1000.0);
+                }
+                if case.status == TestStatus::Passed {
+                    return;
+                }
+                lines.join("\n")
+            }
+            _ => lines.join("\n"),
+        };
+
+        let output = output.trim_matches('\n').trim_end();
+        if !output.is_empty() {
+            case.output = Some(output.to_owned());
+        }
+    }
+
+    fn push_json(&mut self, line: &str) {
+        let Ok(event) = serde_json::from_str::<LibtestEvent>(line) else {
+            return;
+        };
+        let status = match event.event.as_str() {
+            "ok" => TestStatus::Passed,
+            "failed" | "timeout" => TestStatus::Failed,
+            "ignored" => TestStatus::Skipped,
+            _ => return,
+        };
+        let (Some(name), "test") = (event.name, event.kind.as_str()) else {
+            return;
+        };
+
+        self.add(TestCase {
+            duration: event.exec_time,
+            output: event
+                .stdout
+                .map(|v| v.trim_end().to_owned())
+                .filter(|v| !v.is_empty()),
+            ..TestCase::new(&name, status)
+        });
+    }
+
+    fn push_tap(&mut self, indent: usize, passed: bool, number: usize, description: &str) {
+        // Subtest comment at the same indentation names this test, lower ones its parents
+        let suite = self
+            .subtests
+            .iter()
+            .rev()
+            .find(|(v, _)| *v < indent)
+            .map(|(_, v)| v.clone());
+        self.subtests.retain(|(v, _)| *v < indent);
+
+        // Parent result follows results of its subtests, only leaves are kept
+        let is_parent = self.tap_indent.is_some_and(|v| v > indent);
+        self.tap_indent = Some(indent);
+        if is_parent {
+            self.capture = Some((Capture::TapResult, None, vec![]));
+            return;
+        }
+
+        let description = description.trim_start();
+        let description = description.strip_prefix("- ").unwrap_or(description);
+        let (name, directive) = match description.split_once('#') {
+            Some((name, directive)) => (name.trim(), directive.trim().to_uppercase()),
+            None => (description.trim(), String::new()),
+        };
+        let name = match name {
+            "" => format!("test {}", number),
+            name => name.to_owned(),
+        };
+        // Failing TODO tests are expected to fail
+        let status = if directive.starts_with("SKIP") || (!passed && directive.starts_with("TODO"))
+        {
+            TestStatus::Skipped
+        } else if passed {
+            TestStatus::Passed
+        } else {
+            TestStatus::Failed
+        };
+
+        let index = self.add(TestCase {
+            suite,
+            ..TestCase::new(&name, status)
+        });
+        self.capture = Some((Capture::TapResult, Some(index), vec![]));
+    }
+
+    fn push_ctest(&mut self, result: &str) {
+        let Some(position) = result.find("***").or_else(|| result.find(" Passed")) else {
+            return;
+        };
+        let name = result[..position].trim_end_matches([' ', '.']);
+        let outcome = result[position..].trim_start_matches([' ', '*']);
+
+        let status = if outcome.starts_with("Passed") {
+            TestStatus::Passed
+        } else if outcome.starts_with("Skipped") || outcome.starts_with("Not Run") {
+            TestStatus::Skipped
+        } else {
+            TestStatus::Failed
+        };
+        let duration = outcome
+            .strip_suffix(" sec")
+            .and_then(|v| v.rsplit(' ').next())
+            .and_then(|v| v.parse().ok());
+
+        let index = self.add(TestCase {
+            duration,
+            ..TestCase::new(name, status)
+        });
+        if status != TestStatus::Passed {
+            self.capture = Some((Capture::Ctest, Some(index), vec![]));
+        }
+    }
+
+    fn add(&mut self, case: TestCase) -> usize {
+        let suite = case.suite.clone().or(self.suite.clone());
+        self.report.cases.push(TestCase { suite, ..case });
+        self.report.cases.len() - 1
+    }
+
+    /// Last case with name in current suite
+    fn find(&self, name: &str) -> Option<usize> {
+        self.report
+            .cases
+            .iter()
+            .rposition(|v| v.name == name && v.suite == self.suite)
+    }
+
+    pub fn finish(mut self) -> TestReport {
+        self.end_capture();
+        self.report
+    }
+}
+
+/// Suite from lines like `Running unittests src/lib.rs (target/debug/deps/ciri-1f2)`
+fn cargo_suite(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    if let Some(name) = line.strip_prefix("Doc-tests ") {
+        return Some(format!("doc-tests {}", name.trim()));
+    }
+
+    let target = line.strip_prefix("Running ")?;
+    let (target, _) = target.rsplit_once(" (")?;
+    Some(target.trim_start_matches("unittests ").to_owned())
+}
+
+/// Report from whole output of a test run
+pub fn parse_test_output(output: &str) -> TestReport {
+    let mut parser = TestOutputParser::new();
+    for line in output.lines() {
+        parser.push(line);
+    }
+    parser.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(report: &TestReport) -> Vec<(String, TestStatus)> {
+        report
+            .cases
+            .iter()
+            .map(|v| (v.full_name(), v.status))
+            .collect()
+    }
+
+    #[test]
+    fn libtest_test() {
+        let output = "   Compiling lt v0.1.0 (/tmp/lt)
+     Running unittests src/lib.rs (target/debug/deps/lt-d08e8b453f7c5fff)
+
+running 4 tests
+test a ... ok
+test tests::b ... FAILED
+test c ... ignored
+test d ... ignored, slow
+
+failures:
+
+---- tests::b stdout ----
+out
+
+thread 'tests::b' panicked at src/lib.rs:2:34:
+assertion failed
+
+
+failures:
+    tests::b
+
+test result: FAILED. 1 passed; 1 failed; 2 ignored; 0 measured; 0 filtered out
+
+   Doc-tests lt
+
+running 1 test
+test src/lib.rs - f (line 3) ... ok <0.250s>
+";
+        let report = parse_test_output(output);
+        assert_eq!(
+            summary(&report),
+            vec![
+                ("src/lib.rs::a".to_owned(), TestStatus::Passed),
+                ("src/lib.rs::tests::b".to_owned(), TestStatus::Failed),
+                ("src/lib.rs::c".to_owned(), TestStatus::Skipped),
+                ("src/lib.rs::d".to_owned(), TestStatus::Skipped),
+                (
+                    "doc-tests lt::src/lib.rs - f (line 3)".to_owned(),
+                    TestStatus::Passed
+                ),
+            ]
+        );
+        assert_eq!(
+            report.cases[1].output.as_deref(),
+            Some("out\n\nthread 'tests::b' panicked at src/lib.rs:2:34:\nassertion failed")
+        );
+        assert_eq!(report.cases[4].duration, Some(0.25));
+        assert_eq!(report.to_string(), "2 passed, 1 failed, 2 skipped");
+    }
+
+    #[test]
+    fn libtest_json_test() {
+        let output = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "a" }
+{ "type": "test", "name": "a", "event": "ok", "exec_time": 0.5 }
+{ "type": "test", "name": "b", "event": "failed", "stdout": "panicked at src/lib.rs:2:5\n" }
+{ "type": "test", "name": "c", "event": "ignored" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1 }
+"#;
+        let report = parse_test_output(output);
+        assert_eq!(
+            summary(&report),
+            vec![
+                ("a".to_owned(), TestStatus::Passed),
+                ("b".to_owned(), TestStatus::Failed),
+                ("c".to_owned(), TestStatus::Skipped),
+            ]
+        );
+        assert_eq!(report.cases[0].duration, Some(0.5));
+        assert_eq!(
+            report.cases[1].output.as_deref(),
+            Some("panicked at src/lib.rs:2:5")
+        );
+    }
+
+    #[test]
+    fn tap_test() {
+        let output = "TAP version 13
+# Subtest: math
+    # Subtest: adds
+    ok 1 - adds
+      ---
+      duration_ms: 1.5
+      ...
+    # Subtest: divides
+    not ok 2 - divides
+      ---
+      duration_ms: 2
+      error: 'Expected 2'
+      ...
+    1..2
+not ok 1 - math
+  ---
+  duration_ms: 5
+  ...
+ok 2 - skipped one # SKIP not ready
+not ok 3 known bug # TODO
+ok 4
+1..4
+";
+        let report = parse_test_output(output);
+        assert_eq!(
+            summary(&report),
+            vec![
+                ("math::adds".to_owned(), TestStatus::Passed),
+                ("math::divides".to_owned(), TestStatus::Failed),
+                ("skipped one".to_owned(), TestStatus::Skipped),
+                ("known bug".to_owned(), TestStatus::Skipped),
+                ("test 4".to_owned(), TestStatus::Passed),
+            ]
+        );
+        assert_eq!(report.cases[0].duration, Some(0.0015));
+        assert_eq!(report.cases[0].output, None);
+        assert_eq!(
+            report.cases[1].output.as_deref(),
+            Some("duration_ms: 2\nerror: 'Expected 2'")
+        );
+
+        // Output of go test isn't TAP
+        assert!(parse_test_output("ok  \texample.com/pkg\t0.01s").is_empty());
+    }
+
+    #[test]
+    fn ctest_test() {
+        let output = "Test project /tmp/p/build
+    Start 1: unit
+1/3 Test #1: unit .............................   Passed    0.01 sec
+    Start 2: integration
+2/3 Test #2: integration ......................***Failed    1.50 sec
+main.c:5: expected 2
+
+    Start 3: slow
+3/3 Test #3: slow .............................***Not Run (Disabled)   0.00 sec
+
+33% tests passed, 1 tests failed out of 3
+";
+        let report = parse_test_output(output);
+        assert_eq!(
+            summary(&report),
+            vec![
+                ("unit".to_owned(), TestStatus::Passed),
+                ("integration".to_owned(), TestStatus::Failed),
+                ("slow".to_owned(), TestStatus::Skipped),
+            ]
+        );
+        assert_eq!(report.cases[1].duration, Some(1.5));
+        assert_eq!(
+            report.cases[1].output.as_deref(),
+            Some("main.c:5: expected 2")
+        );
+    }
+}
@@ -9,7 +9,7 @@ use super::Res;
 
 type VersionIdentifier<'a> = Option<Vec<(String, &'a str)>>;
 
-pub fn version_identifier_parser(input: &str) -> Res<&str, (u64, VersionIdentifier)> {
+pub fn version_identifier_parser(input: &str) -> Res<&str, (u64, VersionIdentifier<'_>)> {
     context(
         "Version Identifier",
         tuple((
@@ -24,7 +24,7 @@ pub fn version_identifier_parser(input: &str) -> Res<&str, (u64, VersionIdentifi
     )(input)
 }
 
-pub fn full_version_parser(input: &str) -> Res<&str, (u64, u64, u64, VersionIdentifier)> {
+pub fn full_version_parser(input: &str) -> Res<&str, (u64, u64, u64, VersionIdentifier<'_>)> {
     context(
         "Full Version",
         map(
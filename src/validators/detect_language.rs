@@ -1,3 +1,4 @@
+use crate::entities::task_runner::TaskRunner;
 use crate::LANGUAGES;
 use std::collections::HashMap;
 use std::fs;
@@ -12,19 +13,45 @@ pub fn detect_language() -> miette::Result<Vec<String>> {
     let mut managers: Vec<&str> = Vec::new();
 
     for (key, val) in LANGUAGES.iter() {
-        if paths
-            .iter()
-            .filter(|v| val.contains(&format!("{}", v).strip_prefix("./").unwrap()))
-            .any(|v| val.contains(&&v[2..]))
-        {
+        if paths.iter().any(|v| identifies_project(v, val)) {
             managers.push(key);
         }
     }
 
+    // Makefile or justfile is used only when project has nothing better
+    if managers.is_empty() {
+        for runner in TaskRunner::detect(Path::new(".")) {
+            managers.push(match runner {
+                TaskRunner::Just => "just",
+                TaskRunner::Make => "make",
+            });
+        }
+    }
+
     filter_false(&managers, &paths)
 }
 
-fn filter_false(langs: &Vec<&str>, paths: &Vec<String>) -> miette::Result<Vec<String>> {
+/// Extensions that make a project on their own, languages like lua or single C files don't
+/// have a manifest with fixed name
+const PROJECT_EXTENSIONS: [&str; 6] = ["rockspec", "sc", "scala", "c", "cpp", "c++"];
+
+/// Path is a file or directory of language, other sources don't decide about the project
+fn identifies_project(path: &str, files: &[&str]) -> bool {
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+    files.contains(&name) || (PROJECT_EXTENSIONS.contains(&extension) && files.contains(&extension))
+}
+
+fn filter_false(langs: &[&str], paths: &[String]) -> miette::Result<Vec<String>> {
     let mut hash: HashMap<&str, usize> = HashMap::new();
     langs.iter().for_each(|lang| {
         hash.insert(lang, count_paths_for_language(lang, paths));
@@ -35,18 +62,18 @@ fn filter_false(langs: &Vec<&str>, paths: &Vec<String>) -> miette::Result<Vec<St
         Some(max_value) => Ok(hash
             .iter()
             .filter(|v| *v.1 == max_value)
-            .map(|k| format!("{}", k.0))
+            .map(|k| k.0.to_string())
             .collect()),
         None => miette::bail!("No manager found"),
     }
 }
 
-fn count_paths_for_language(language: &str, paths: &Vec<String>) -> usize {
+fn count_paths_for_language(language: &str, paths: &[String]) -> usize {
     paths
         .iter()
         .filter(|v| {
             let langs = LANGUAGES.get(language).unwrap_or(&vec![]).clone();
-            if langs.contains(
+            langs.contains(
                 &Path::new(v)
                     .file_name()
                     .unwrap_or_default()
@@ -58,11 +85,7 @@ fn count_paths_for_language(language: &str, paths: &Vec<String>) -> usize {
                     .unwrap_or_default()
                     .to_str()
                     .unwrap_or_default(),
-            ) {
-                true
-            } else {
-                false
-            }
+            )
         })
         .count()
 }
@@ -73,11 +96,8 @@ mod tests {
 
     #[test]
     fn filter_false_test() {
-        let mut managers_same_file = filter_false(
-            &vec!["npm", "pnpm", "yarn"],
-            &vec!["package.json".to_owned()],
-        )
-        .unwrap();
+        let mut managers_same_file =
+            filter_false(&["npm", "pnpm", "yarn"], &["package.json".to_owned()]).unwrap();
         // Sort is needed for the same result every time
         managers_same_file.sort();
         assert_eq!(
@@ -85,17 +105,14 @@ mod tests {
             vec!["npm".to_owned(), "pnpm".to_owned(), "yarn".to_owned()]
         );
 
-        let mut managers_one_match = filter_false(
-            &vec!["npm", "pnpm", "yarn"],
-            &vec!["pnpm-lock.yaml".to_owned()],
-        )
-        .unwrap();
+        let mut managers_one_match =
+            filter_false(&["npm", "pnpm", "yarn"], &["pnpm-lock.yaml".to_owned()]).unwrap();
         managers_one_match.sort();
         assert_eq!(managers_one_match, vec!["pnpm".to_owned()]);
 
         let mut managers_multiple_matches = filter_false(
-            &vec!["npm", "pnpm", "yarn"],
-            &vec!["package-lock.json".to_owned(), "pnpm-lock.yaml".to_owned()],
+            &["npm", "pnpm", "yarn"],
+            &["package-lock.json".to_owned(), "pnpm-lock.yaml".to_owned()],
         )
         .unwrap();
         managers_multiple_matches.sort();
@@ -105,8 +122,8 @@ mod tests {
         );
 
         let mut managers_multiple_matches = filter_false(
-            &vec!["cargo", "npm"],
-            &vec!["package-lock.json".to_owned(), "Cargo.toml".to_owned()],
+            &["cargo", "npm"],
+            &["package-lock.json".to_owned(), "Cargo.toml".to_owned()],
         )
         .unwrap();
         managers_multiple_matches.sort();
@@ -120,20 +137,51 @@ mod tests {
     fn count_paths_for_language_test() {
         let paths_number = count_paths_for_language(
             "npm",
-            &vec!["./package.json".to_owned(), "package-lock.json".to_owned()],
+            &["./package.json".to_owned(), "package-lock.json".to_owned()],
         );
         assert_eq!(paths_number, 2);
 
-        let no_paths = count_paths_for_language("npm", &vec![]);
+        let no_paths = count_paths_for_language("npm", &[]);
         assert_eq!(no_paths, 0);
 
         let invalid_manager = count_paths_for_language(
             "invalid",
-            &vec!["./package.json".to_owned(), "package-lock.json".to_owned()],
+            &["./package.json".to_owned(), "package-lock.json".to_owned()],
         );
         assert_eq!(invalid_manager, 0);
 
-        let paths_number = count_paths_for_language("cargo", &vec!["main.rs".to_owned()]);
+        let paths_number = count_paths_for_language("cargo", &["main.rs".to_owned()]);
         assert_eq!(paths_number, 1);
     }
+
+    #[test]
+    fn build_sbt_only_scala_test() {
+        let paths = vec!["./build.sbt".to_owned()];
+        assert_eq!(count_paths_for_language("java", &paths), 0);
+        assert_eq!(count_paths_for_language("scala", &paths), 1);
+
+        let scripts = vec!["./hello.sc".to_owned(), "./project.scala".to_owned()];
+        assert_eq!(count_paths_for_language("scala", &scripts), 2);
+    }
+
+    #[test]
+    fn identifies_project_test() {
+        let lua = LANGUAGES.get("lua").unwrap();
+        assert!(identifies_project("./foo-1.0-1.rockspec", lua));
+        assert!(identifies_project("./lua", lua));
+        assert!(!identifies_project("./main.lua", lua));
+
+        let python = LANGUAGES.get("python").unwrap();
+        assert!(identifies_project("./pyproject.toml", python));
+        assert!(!identifies_project("./script.py", python));
+    }
+
+    #[test]
+    fn build_system_of_c_test() {
+        let paths = vec!["./meson.build".to_owned(), "./main.c".to_owned()];
+        assert_eq!(filter_false(&["gcc", "g++"], &paths).unwrap(), vec!["gcc"]);
+
+        let paths = vec!["./configure.ac".to_owned(), "./main.cpp".to_owned()];
+        assert_eq!(filter_false(&["gcc", "g++"], &paths).unwrap(), vec!["g++"]);
+    }
 }